@@ -0,0 +1,526 @@
+//! API key storage and scheduled rotation
+//!
+//! The permanent API key is kept inline in the state file, in a file of its
+//! own or in a systemd credential, as configured by `api_key_storage`. When
+//! it is kept elsewhere, the state only records where (`api_key_ref`), so
+//! state files and their backups don't contain the key.
+//!
+//! Every image ships the same bootstrap key, so rotating away from it only
+//! counts as complete once Homarr is seen rejecting it. That is recorded in
+//! state (`bootstrap_key_revoked_at`), after which the bootstrap key file is
+//! kept in use or ignored as `bootstrap_key_file_policy` says.
+//!
+//! In watch mode the permanent API key is replaced once it is older than
+//! `api_key_rotation_interval`. The new key is created and verified, then
+//! saved to state, and only then is the old key deleted in Homarr, so a
+//! failure at any step leaves a working key in state. The old key is
+//! recorded as retired along with the new one, so if it is not deleted (the
+//! request fails or the adapter stops) it is deleted on a later check.
+
+use chrono::{DateTime, Utc};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::config::{ApiKeyStorage, BootstrapKeyFilePolicy, Config};
+use crate::error::{AdapterError, Result};
+use crate::homarr::HomarrClient;
+use crate::retry::RequestPolicy;
+use crate::state::{self, State};
+
+/// Where an API key is kept
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyStore {
+    /// Inline in the state file
+    State,
+
+    /// In a file of its own
+    File(PathBuf),
+
+    /// In a systemd credential, by name
+    Credential(String),
+}
+
+impl KeyStore {
+    /// The store configured by `api_key_storage`
+    pub fn from_config(config: &Config) -> Self {
+        match config.api_key_storage {
+            ApiKeyStorage::State => Self::State,
+            ApiKeyStorage::File => Self::File(PathBuf::from(&config.api_key_file)),
+            ApiKeyStorage::Credential => Self::Credential(config.api_key_credential.clone()),
+        }
+    }
+
+    /// The store a state refers to
+    pub fn from_state(state: &State) -> Result<Self> {
+        let Some(reference) = state.api_key_ref.as_deref() else {
+            return Ok(Self::State);
+        };
+        match reference.split_once(':') {
+            Some(("file", path)) => Ok(Self::File(PathBuf::from(path))),
+            Some(("credential", name)) => Ok(Self::Credential(name.to_string())),
+            _ => Err(AdapterError::State(format!(
+                "Unknown API key reference '{}'",
+                reference
+            ))),
+        }
+    }
+
+    /// Reference recorded in state (`None` for inline keys)
+    fn reference(&self) -> Option<String> {
+        match self {
+            Self::State => None,
+            Self::File(path) => Some(format!("file:{}", path.display())),
+            Self::Credential(name) => Some(format!("credential:{}", name)),
+        }
+    }
+
+    /// Whether the adapter can store a new key here
+    pub fn is_writable(&self) -> bool {
+        !matches!(self, Self::Credential(_))
+    }
+
+    /// Read the key, `None` if there is none
+    fn read(&self, state: &State) -> Result<Option<String>> {
+        let path = match self {
+            Self::State => return Ok(state.api_key.clone()),
+            Self::File(path) => path.clone(),
+            Self::Credential(name) => credential_path(name)?,
+        };
+        match fs::read_to_string(&path) {
+            Ok(contents) => Ok(Some(contents.trim().to_string()).filter(|key| !key.is_empty())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(AdapterError::Config(format!(
+                "Failed to read API key from {}: {}",
+                path.display(),
+                e
+            ))),
+        }
+    }
+
+    /// Store a key and point the state at it (the state is not saved)
+    fn write(&self, state: &mut State, api_key: &str) -> Result<()> {
+        match self {
+            Self::State => state.api_key = Some(api_key.to_string()),
+            Self::File(path) => {
+                state::write_private(path, format!("{}\n", api_key).as_bytes())?;
+                state.api_key = None;
+            }
+            Self::Credential(name) => {
+                return Err(AdapterError::Config(format!(
+                    "API key is read from systemd credential '{}' and can't be replaced",
+                    name
+                )))
+            }
+        }
+        state.api_key_ref = self.reference();
+        Ok(())
+    }
+}
+
+impl fmt::Display for KeyStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::State => write!(f, "state file"),
+            Self::File(path) => write!(f, "file {}", path.display()),
+            Self::Credential(name) => write!(f, "systemd credential '{}'", name),
+        }
+    }
+}
+
+/// Path of a systemd credential passed with `LoadCredential=`
+fn credential_path(name: &str) -> Result<PathBuf> {
+    let dir = std::env::var_os("CREDENTIALS_DIRECTORY").ok_or_else(|| {
+        AdapterError::Config(format!(
+            "API key credential '{}' is unavailable: $CREDENTIALS_DIRECTORY is not set",
+            name
+        ))
+    })?;
+    Ok(Path::new(&dir).join(name))
+}
+
+/// The API key, read from wherever the state says it is kept
+pub fn stored_key(state: &State) -> Result<Option<String>> {
+    KeyStore::from_state(state)?.read(state)
+}
+
+/// Store a new API key in the configured store and save the state
+///
+/// Once the key itself is stored, failing to save the state only loses its
+/// creation time, so that is logged rather than returned.
+pub fn store_key(config: &Config, state: &mut State, api_key: &str) -> Result<()> {
+    let store = KeyStore::from_config(config);
+    store.write(state, api_key)?;
+    state.api_key_created_at = Some(Utc::now());
+
+    match state.save(&config.state_file) {
+        Err(e) if store != KeyStore::State => {
+            tracing::warn!("API key stored, but failed to save state: {}", e);
+            Ok(())
+        }
+        result => result,
+    }
+}
+
+/// Move the API key to the store configured by `api_key_storage`
+///
+/// Keys are moved from the state file (as written by older versions) or a
+/// previously configured store. If the state has no key, a key already in the
+/// configured store (e.g. a provisioned credential) is adopted. The old copy
+/// is removed, including from the state file's backup.
+pub fn migrate_storage(config: &Config, state: &mut State) -> Result<()> {
+    let current = KeyStore::from_state(state)?;
+    let target = KeyStore::from_config(config);
+    if current == target {
+        return Ok(());
+    }
+
+    match current.read(state)? {
+        Some(api_key) if target.is_writable() => {
+            target.write(state, &api_key)?;
+            tracing::info!("Moved API key from {} to {}", current, target);
+        }
+        _ if target.read(state).ok().flatten().is_some() => {
+            state.api_key = None;
+            state.api_key_ref = target.reference();
+            tracing::info!("Using the API key in {}", target);
+        }
+        Some(_) => {
+            tracing::warn!(
+                "API key storage is set to {}, but it holds no key; keeping the key in {}",
+                target,
+                current
+            );
+            return Ok(());
+        }
+        None => return Ok(()),
+    }
+
+    state.save(&config.state_file)?;
+    if current == KeyStore::State {
+        // The first save moved the old state, key included, to the backup
+        state.save(&config.state_file)?;
+    }
+    if let KeyStore::File(path) = current {
+        if let Err(e) = fs::remove_file(&path).or_else(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => Ok(()),
+            _ => Err(e),
+        }) {
+            tracing::warn!(
+                "Failed to remove old API key file {}: {}",
+                path.display(),
+                e
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Check if the bootstrap key file must not be used any more
+pub fn bootstrap_key_ignored(config: &Config, state: &State) -> bool {
+    config.bootstrap_key_file_policy != BootstrapKeyFilePolicy::Keep
+        && state.bootstrap_key_revoked_at.is_some()
+}
+
+/// Make sure Homarr no longer accepts the bootstrap API key
+///
+/// `client` must be authenticated with the permanent key. If the bootstrap
+/// key still works, deleting it is retried. Once it is rejected, that is
+/// recorded in state, which lets `bootstrap_key_ignored()` apply the key file
+/// policy; until then callers should check again on the next run.
+pub async fn verify_bootstrap_revoked(
+    client: &HomarrClient,
+    config: &Config,
+    state: &mut State,
+    bootstrap_key: &str,
+) -> Result<()> {
+    let mut probe = HomarrClient::new(&config.homarr_url, RequestPolicy::from_config(config))?;
+    probe.set_api_key(bootstrap_key.to_string());
+
+    if probe.check_api_key().await? {
+        tracing::error!(
+            "SECURITY: Homarr still accepts the bootstrap API key shipped with every image, deleting it again"
+        );
+        if let Some(id) = key_id(bootstrap_key) {
+            if let Err(e) = client.delete_api_key(id).await {
+                tracing::error!("SECURITY: Failed to delete the bootstrap API key: {}", e);
+            }
+        }
+        if probe.check_api_key().await? {
+            tracing::error!(
+                "SECURITY: The bootstrap API key is still valid; delete it in Homarr's API key settings"
+            );
+            return Ok(());
+        }
+    }
+
+    tracing::info!("Verified that Homarr rejects the bootstrap API key");
+    state.bootstrap_key_revoked_at = Some(Utc::now());
+    state.save(&config.state_file)?;
+    Ok(())
+}
+
+/// ID part of an API key ("{id}.{token}")
+pub fn key_id(api_key: &str) -> Option<&str> {
+    api_key
+        .split_once('.')
+        .map(|(id, _)| id)
+        .filter(|id| !id.is_empty())
+}
+
+/// Check if the stored API key is old enough to be replaced
+///
+/// Keys of unknown age (stored before creation times were recorded) are due.
+pub fn rotation_due(state: &State, interval: Duration, now: DateTime<Utc>) -> bool {
+    match state.api_key_created_at {
+        // A creation time in the future (clock changes) is not due
+        Some(created) => (now - created).to_std().is_ok_and(|age| age >= interval),
+        None => true,
+    }
+}
+
+/// Rotate the stored API key if rotation is enabled and the key is due
+pub async fn rotate_if_due(config: &Config) -> Result<()> {
+    if config.api_key_rotation_interval == 0 {
+        return Ok(());
+    }
+
+    let mut state = State::load(&config.state_file)?;
+    if !KeyStore::from_state(&state)?.is_writable() {
+        tracing::debug!("API key is read-only, not rotating it");
+        return Ok(());
+    }
+    let Some(api_key) = stored_key(&state)? else {
+        return Ok(());
+    };
+    let mut client = HomarrClient::new(&config.homarr_url, RequestPolicy::from_config(config))?;
+    client.set_api_key(api_key);
+
+    delete_retired_keys(&client, config, &mut state).await;
+
+    let interval = Duration::from_secs(config.api_key_rotation_interval);
+    if !rotation_due(&state, interval, Utc::now()) {
+        return Ok(());
+    }
+    rotate(&mut client, config, &mut state).await
+}
+
+/// Replace the stored API key with a new one
+async fn rotate(client: &mut HomarrClient, config: &Config, state: &mut State) -> Result<()> {
+    let Some(old_key) = stored_key(state)? else {
+        return Ok(());
+    };
+    tracing::info!("Rotating API key");
+
+    // The old key stays in use until the new one is verified and saved
+    let new_key = client.create_api_key().await?;
+    client.set_api_key(new_key.clone());
+    match client.check_api_key().await {
+        Ok(true) => {}
+        result => {
+            client.set_api_key(old_key);
+            discard_key(client, &new_key).await;
+            let reason = match result {
+                Err(e) => e.to_string(),
+                _ => "rejected by Homarr".to_string(),
+            };
+            return Err(AdapterError::HomarrApi(format!(
+                "New API key could not be verified, keeping the old one: {}",
+                reason
+            )));
+        }
+    }
+
+    // Retire the old key in the same save that stores the new one, so a
+    // crash before it is deleted leaves it to `delete_retired_keys()`
+    let old_id = key_id(&old_key).map(str::to_string);
+    let (old_inline, old_created_at) = (state.api_key.clone(), state.api_key_created_at);
+    let old_retired = state.retired_api_key_ids.clone();
+    state.retired_api_key_ids.extend(old_id.clone());
+    if let Err(e) = store_key(config, state, &new_key) {
+        // The old key is still the stored one, and keeps working
+        state.api_key = old_inline;
+        state.api_key_created_at = old_created_at;
+        state.retired_api_key_ids = old_retired;
+        client.set_api_key(old_key);
+        discard_key(client, &new_key).await;
+        return Err(e);
+    }
+    tracing::info!("Switched to new API key");
+
+    // From here on the new key is the one in use; the old one only has to go
+    if let Some(old_id) = old_id {
+        if let Err(e) = client.delete_api_key(&old_id).await {
+            tracing::warn!(
+                "Failed to delete old API key '{}', will retry: {}",
+                old_id,
+                e
+            );
+        } else {
+            tracing::info!("Deleted old API key '{}'", old_id);
+            state.retired_api_key_ids.retain(|id| *id != old_id);
+            state.save(&config.state_file)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Delete a key that was created but is not going to be used
+async fn discard_key(client: &HomarrClient, api_key: &str) {
+    let Some(id) = key_id(api_key) else {
+        return;
+    };
+    if let Err(e) = client.delete_api_key(id).await {
+        tracing::warn!("Failed to delete unused API key '{}': {}", id, e);
+    }
+}
+
+/// Retry deleting old keys that a previous rotation could not delete
+async fn delete_retired_keys(client: &HomarrClient, config: &Config, state: &mut State) {
+    if state.retired_api_key_ids.is_empty() {
+        return;
+    }
+
+    let mut remaining = Vec::new();
+    for id in std::mem::take(&mut state.retired_api_key_ids) {
+        match client.delete_api_key(&id).await {
+            Ok(()) => tracing::info!("Deleted old API key '{}'", id),
+            Err(e) => {
+                tracing::warn!("Failed to delete old API key '{}': {}", id, e);
+                remaining.push(id);
+            }
+        }
+    }
+    state.retired_api_key_ids = remaining;
+
+    if let Err(e) = state.save(&config.state_file) {
+        tracing::warn!("Failed to save state after deleting old API keys: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+    use tempfile::TempDir;
+
+    fn file_config(dir: &TempDir) -> Config {
+        Config {
+            state_file: dir.path().join("state.json").display().to_string(),
+            api_key_storage: ApiKeyStorage::File,
+            api_key_file: dir.path().join("api-key").display().to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_migrate_inline_key_to_file() {
+        let dir = TempDir::new().unwrap();
+        let config = file_config(&dir);
+        let mut state = State {
+            api_key: Some("id.secret".to_string()),
+            ..Default::default()
+        };
+        state.save(&config.state_file).unwrap();
+
+        migrate_storage(&config, &mut state).unwrap();
+
+        assert_eq!(state.api_key, None);
+        assert_eq!(stored_key(&state).unwrap().as_deref(), Some("id.secret"));
+        let mode = fs::metadata(&config.api_key_file)
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o600);
+        // Neither the state file nor its backup holds the key any more
+        for path in [
+            config.state_file.clone(),
+            format!("{}.bak", config.state_file),
+        ] {
+            assert!(!fs::read_to_string(path).unwrap().contains("secret"));
+        }
+
+        let reloaded = State::load(&config.state_file).unwrap();
+        assert_eq!(stored_key(&reloaded).unwrap().as_deref(), Some("id.secret"));
+    }
+
+    #[test]
+    fn test_migrate_file_key_back_to_state() {
+        let dir = TempDir::new().unwrap();
+        let mut config = file_config(&dir);
+        let mut state = State::default();
+        store_key(&config, &mut state, "id.secret").unwrap();
+        assert!(state.api_key_created_at.is_some());
+
+        config.api_key_storage = ApiKeyStorage::State;
+        migrate_storage(&config, &mut state).unwrap();
+
+        assert_eq!(state.api_key.as_deref(), Some("id.secret"));
+        assert_eq!(state.api_key_ref, None);
+        assert!(!Path::new(&config.api_key_file).exists());
+    }
+
+    #[test]
+    fn test_migrate_adopts_key_in_configured_store() {
+        let dir = TempDir::new().unwrap();
+        let config = file_config(&dir);
+        fs::write(&config.api_key_file, "id.secret\n").unwrap();
+        let mut state = State::default();
+
+        migrate_storage(&config, &mut state).unwrap();
+
+        assert_eq!(stored_key(&state).unwrap().as_deref(), Some("id.secret"));
+    }
+
+    #[test]
+    fn test_bootstrap_key_ignored() {
+        let mut config = Config::default();
+        let mut state = State::default();
+        config.bootstrap_key_file_policy = BootstrapKeyFilePolicy::Ignore;
+        assert!(!bootstrap_key_ignored(&config, &state));
+
+        state.bootstrap_key_revoked_at = Some(Utc::now());
+        assert!(bootstrap_key_ignored(&config, &state));
+
+        config.bootstrap_key_file_policy = BootstrapKeyFilePolicy::Keep;
+        assert!(!bootstrap_key_ignored(&config, &state));
+    }
+
+    #[test]
+    fn test_unknown_key_reference() {
+        let state = State {
+            api_key_ref: Some("vault:homarr".to_string()),
+            ..Default::default()
+        };
+        assert!(stored_key(&state).is_err());
+        assert!(!KeyStore::Credential("homarr-api-key".to_string()).is_writable());
+    }
+
+    #[test]
+    fn test_key_id() {
+        assert_eq!(key_id("abc123.token"), Some("abc123"));
+        assert_eq!(key_id("abc123"), None);
+        assert_eq!(key_id(".token"), None);
+    }
+
+    #[test]
+    fn test_rotation_due() {
+        let now = Utc::now();
+        let day = Duration::from_secs(86400);
+        let mut state = State::default();
+
+        // Keys from before creation times were recorded
+        assert!(rotation_due(&state, day, now));
+
+        state.api_key_created_at = Some(now - chrono::Duration::hours(1));
+        assert!(!rotation_due(&state, day, now));
+
+        state.api_key_created_at = Some(now - chrono::Duration::days(2));
+        assert!(rotation_due(&state, day, now));
+
+        state.api_key_created_at = Some(now + chrono::Duration::days(2));
+        assert!(!rotation_due(&state, day, now));
+    }
+}
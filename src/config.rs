@@ -4,7 +4,7 @@ use serde::Deserialize;
 use std::fs;
 use std::path::Path;
 
-use crate::error::Result;
+use crate::error::{AdapterError, Result};
 
 /// Main adapter configuration
 #[derive(Debug, Clone, Deserialize)]
@@ -14,6 +14,24 @@ pub struct Config {
     #[serde(default = "default_homarr_url")]
     pub homarr_url: String,
 
+    /// Seconds allowed to connect to Homarr
+    #[serde(default = "default_homarr_connect_timeout")]
+    pub homarr_connect_timeout: u64,
+
+    /// Seconds allowed for a Homarr API request, including the response
+    #[serde(default = "default_homarr_request_timeout")]
+    pub homarr_request_timeout: u64,
+
+    /// Retries of idempotent Homarr requests after connection errors,
+    /// timeouts and 5xx responses
+    #[serde(default = "default_homarr_max_retries")]
+    pub homarr_max_retries: u32,
+
+    /// Milliseconds before the first retry of a Homarr request (doubles with
+    /// each further retry)
+    #[serde(default = "default_homarr_retry_delay_ms")]
+    pub homarr_retry_delay_ms: u64,
+
     /// Path to branding config file
     #[serde(default = "default_branding_file")]
     pub branding_file: String,
@@ -30,6 +48,14 @@ pub struct Config {
     #[serde(default = "default_registry_dir")]
     pub registry_dir: String,
 
+    /// Discover apps from Docker containers with `homarr.*` labels
+    #[serde(default = "default_docker_labels")]
+    pub docker_labels: bool,
+
+    /// Reflow adapter-owned tiles into priority order on every sync
+    #[serde(default)]
+    pub relayout_on_sync: bool,
+
     /// Path to Authelia users database file
     #[serde(default = "default_authelia_users_db")]
     pub authelia_users_db: String,
@@ -38,6 +64,27 @@ pub struct Config {
     #[serde(default = "default_bootstrap_api_key_file")]
     pub bootstrap_api_key_file: String,
 
+    /// What to do with the bootstrap API key file once rotation is verified
+    #[serde(default)]
+    pub bootstrap_key_file_policy: BootstrapKeyFilePolicy,
+
+    /// Where the permanent API key is kept
+    #[serde(default)]
+    pub api_key_storage: ApiKeyStorage,
+
+    /// API key file for `api_key_storage = "file"`
+    #[serde(default = "default_api_key_file")]
+    pub api_key_file: String,
+
+    /// systemd credential name for `api_key_storage = "credential"`
+    #[serde(default = "default_api_key_credential")]
+    pub api_key_credential: String,
+
+    /// Replace the API key with a new one once it is this many seconds old
+    /// (for watch mode, 0 disables rotation)
+    #[serde(default)]
+    pub api_key_rotation_interval: u64,
+
     /// Enable debug logging
     #[serde(default)]
     pub debug: bool,
@@ -46,15 +93,73 @@ pub struct Config {
     #[serde(default = "default_sync_interval")]
     pub sync_interval: u64,
 
+    /// Seconds before retrying a failed initial sync (for watch mode); doubles
+    /// with each failure, up to `sync_interval`
+    #[serde(default = "default_sync_retry_delay")]
+    pub sync_retry_delay: u64,
+
     /// Startup delay in seconds before first sync (for watch mode)
     #[serde(default = "default_startup_delay")]
     pub startup_delay: u64,
+
+    /// Health endpoint address for watch mode: a loopback `host:port` or
+    /// `unix:/path/to/socket` (disabled if unset)
+    #[serde(default)]
+    pub health_listen: Option<String>,
+}
+
+/// What to do with the bootstrap API key file once Homarr rejects the key
+///
+/// The file belongs to the branding package and is read-only to the service,
+/// so it is never modified or deleted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BootstrapKeyFilePolicy {
+    /// Leave the file, and rotate from it again if the permanent key is
+    /// rejected (e.g. after Homarr's database was restored)
+    #[default]
+    Keep,
+
+    /// Leave the file, but never read it again
+    Ignore,
+}
+
+/// Where the permanent API key is kept
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ApiKeyStorage {
+    /// Inline in the state file
+    #[default]
+    State,
+
+    /// In `api_key_file`, readable only by the adapter
+    File,
+
+    /// In the systemd credential `api_key_credential` (`LoadCredential=`),
+    /// which the adapter can't replace
+    Credential,
 }
 
 fn default_homarr_url() -> String {
     "http://localhost:7575".to_string()
 }
 
+fn default_homarr_connect_timeout() -> u64 {
+    5
+}
+
+fn default_homarr_request_timeout() -> u64 {
+    30
+}
+
+fn default_homarr_max_retries() -> u32 {
+    3
+}
+
+fn default_homarr_retry_delay_ms() -> u64 {
+    500
+}
+
 fn default_branding_file() -> String {
     "/etc/halos-homarr-branding/branding.toml".to_string()
 }
@@ -71,6 +176,10 @@ fn default_registry_dir() -> String {
     "/etc/halos/webapps.d".to_string()
 }
 
+fn default_docker_labels() -> bool {
+    true
+}
+
 fn default_authelia_users_db() -> String {
     "/var/lib/container-apps/halos-authelia-container/data/users_database.yml".to_string()
 }
@@ -79,10 +188,22 @@ fn default_bootstrap_api_key_file() -> String {
     "/etc/halos-homarr-branding/bootstrap-api-key".to_string()
 }
 
+fn default_api_key_file() -> String {
+    "/var/lib/homarr-container-adapter/api-key".to_string()
+}
+
+fn default_api_key_credential() -> String {
+    "homarr-api-key".to_string()
+}
+
 fn default_sync_interval() -> u64 {
     300 // 5 minutes - fallback for missed Docker events
 }
 
+fn default_sync_retry_delay() -> u64 {
+    10
+}
+
 fn default_startup_delay() -> u64 {
     10 // 10 seconds
 }
@@ -91,15 +212,28 @@ impl Default for Config {
     fn default() -> Self {
         Self {
             homarr_url: default_homarr_url(),
+            homarr_connect_timeout: default_homarr_connect_timeout(),
+            homarr_request_timeout: default_homarr_request_timeout(),
+            homarr_max_retries: default_homarr_max_retries(),
+            homarr_retry_delay_ms: default_homarr_retry_delay_ms(),
             branding_file: default_branding_file(),
             state_file: default_state_file(),
             docker_socket: default_docker_socket(),
             registry_dir: default_registry_dir(),
+            docker_labels: default_docker_labels(),
+            relayout_on_sync: false,
             authelia_users_db: default_authelia_users_db(),
             bootstrap_api_key_file: default_bootstrap_api_key_file(),
+            bootstrap_key_file_policy: BootstrapKeyFilePolicy::default(),
+            api_key_storage: ApiKeyStorage::default(),
+            api_key_file: default_api_key_file(),
+            api_key_credential: default_api_key_credential(),
+            api_key_rotation_interval: 0,
             debug: false,
             sync_interval: default_sync_interval(),
+            sync_retry_delay: default_sync_retry_delay(),
             startup_delay: default_startup_delay(),
+            health_listen: None,
         }
     }
 }
@@ -116,7 +250,47 @@ impl Config {
 
         let contents = fs::read_to_string(path)?;
         let config: Config = toml::from_str(&contents)?;
+        config.validate()?;
 
         Ok(config)
     }
+
+    /// Reject settings that can't work
+    fn validate(&self) -> Result<()> {
+        // A zero timeout fails every request instead of disabling the timeout
+        for (name, value) in [
+            ("homarr_connect_timeout", self.homarr_connect_timeout),
+            ("homarr_request_timeout", self.homarr_request_timeout),
+        ] {
+            if value == 0 {
+                return Err(AdapterError::Config(format!(
+                    "{} must be at least 1 second",
+                    name
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zero_timeouts_are_rejected() {
+        assert!(Config::default().validate().is_ok());
+
+        let config = Config {
+            homarr_request_timeout: 0,
+            ..Default::default()
+        };
+        assert!(matches!(config.validate(), Err(AdapterError::Config(_))));
+
+        let config = Config {
+            homarr_connect_timeout: 0,
+            ..Default::default()
+        };
+        assert!(matches!(config.validate(), Err(AdapterError::Config(_))));
+    }
 }
@@ -0,0 +1,246 @@
+//! Docker container discovery
+//!
+//! Containers opt in to the dashboard with `homarr.*` labels. This module
+//! lists containers and turns their labels into app definitions that are
+//! merged with the static registry in `/etc/halos/webapps.d/`.
+
+use bollard::container::ListContainersOptions;
+use bollard::Docker;
+use std::collections::HashMap;
+
+use crate::error::Result;
+use crate::registry::{self, AppDefinition, AppSource, AppType, LayoutConfig, RegistryEntry};
+
+/// Label that must be "true" for a container to be discovered
+pub const LABEL_ENABLE: &str = "homarr.enable";
+/// Display name in Homarr
+pub const LABEL_NAME: &str = "homarr.name";
+/// URL to access the app
+pub const LABEL_URL: &str = "homarr.url";
+/// Optional app description
+pub const LABEL_DESCRIPTION: &str = "homarr.description";
+/// Optional icon URL
+pub const LABEL_ICON: &str = "homarr.icon";
+/// Optional category for grouping
+pub const LABEL_CATEGORY: &str = "homarr.category";
+
+/// Connect to the Docker daemon via its Unix socket
+pub fn connect(socket: &str) -> Result<Docker> {
+    let docker = Docker::connect_with_socket(
+        socket,
+        120, // timeout in seconds
+        bollard::API_DEFAULT_VERSION,
+    )?;
+    Ok(docker)
+}
+
+/// Discover apps from containers with `homarr.enable=true`
+///
+/// Stopped containers are included so that a tile stays on the dashboard
+/// (showing the app as down) until the container is actually removed.
+/// Containers with incomplete or invalid labels are skipped with a warning.
+pub async fn discover_apps(docker: &Docker) -> Result<Vec<RegistryEntry>> {
+    let mut filters = HashMap::new();
+    filters.insert("label".to_string(), vec![format!("{}=true", LABEL_ENABLE)]);
+
+    let options = ListContainersOptions::<String> {
+        all: true,
+        filters,
+        ..Default::default()
+    };
+
+    let containers = docker.list_containers(Some(options)).await?;
+
+    let mut entries = Vec::new();
+    for container in containers {
+        let Some(name) = container
+            .names
+            .as_ref()
+            .and_then(|names| names.first())
+            .map(|name| name.trim_start_matches('/').to_string())
+        else {
+            continue;
+        };
+
+        let labels = container.labels.unwrap_or_default();
+        if let Some(app) = app_from_labels(&name, &labels) {
+            tracing::debug!("Discovered app '{}' from container '{}'", app.name, name);
+            entries.push(RegistryEntry {
+                source: AppSource::Container(name),
+                app,
+            });
+        }
+    }
+
+    tracing::info!("Discovered {} app(s) from container labels", entries.len());
+
+    Ok(entries)
+}
+
+/// Get the state (running, exited, ...) of every container by name
+pub async fn container_states(docker: &Docker) -> Result<HashMap<String, String>> {
+    let options = ListContainersOptions::<String> {
+        all: true,
+        ..Default::default()
+    };
+
+    let containers = docker.list_containers(Some(options)).await?;
+
+    Ok(containers
+        .into_iter()
+        .flat_map(|container| {
+            let state = container.state.unwrap_or_else(|| "unknown".to_string());
+            container
+                .names
+                .unwrap_or_default()
+                .into_iter()
+                .map(move |name| (name.trim_start_matches('/').to_string(), state.clone()))
+        })
+        .collect())
+}
+
+/// Build an app definition from a container's `homarr.*` labels
+///
+/// Returns `None` if the container has not opted in or its labels are invalid.
+/// Label-discovered apps are always visible and use the default layout.
+pub fn app_from_labels(
+    container_name: &str,
+    labels: &HashMap<String, String>,
+) -> Option<AppDefinition> {
+    if labels.get(LABEL_ENABLE).map(String::as_str) != Some("true") {
+        return None;
+    }
+
+    let label = |key: &str| {
+        labels
+            .get(key)
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+    };
+
+    let app = AppDefinition {
+        id: container_name.to_string(),
+        name: label(LABEL_NAME).unwrap_or_default(),
+        url: label(LABEL_URL).unwrap_or_default(),
+        description: label(LABEL_DESCRIPTION),
+        icon_url: label(LABEL_ICON),
+        category: label(LABEL_CATEGORY),
+        visible: true,
+        app_type: AppType {
+            container_name: Some(container_name.to_string()),
+            external: false,
+        },
+        ping_url: None,
+        layout: LayoutConfig::default(),
+    };
+
+    let origin = format!("labels of container '{}'", container_name);
+    if let Err(e) = registry::validate_app(&app, &origin) {
+        tracing::warn!("Ignoring container '{}': {}", container_name, e);
+        return None;
+    }
+
+    Some(app)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_app_from_labels_full() {
+        let labels = labels(&[
+            ("homarr.enable", "true"),
+            ("homarr.name", "Signal K"),
+            ("homarr.url", "http://localhost:3000"),
+            ("homarr.description", "Marine data server"),
+            ("homarr.icon", "/icons/signalk.png"),
+            ("homarr.category", "Marine"),
+        ]);
+
+        let app = app_from_labels("signalk-server", &labels).unwrap();
+        assert_eq!(app.id, "signalk-server");
+        assert_eq!(app.name, "Signal K");
+        assert_eq!(app.url, "http://localhost:3000");
+        assert_eq!(app.description.as_deref(), Some("Marine data server"));
+        assert_eq!(app.icon_url.as_deref(), Some("/icons/signalk.png"));
+        assert_eq!(app.category.as_deref(), Some("Marine"));
+        assert_eq!(app.container_name(), Some("signalk-server"));
+        assert!(app.is_visible());
+        assert!(!app.is_external());
+        assert_eq!(app.priority(), 50);
+    }
+
+    #[test]
+    fn test_app_from_labels_minimal() {
+        let labels = labels(&[
+            ("homarr.enable", "true"),
+            ("homarr.name", "Grafana"),
+            ("homarr.url", "http://localhost:3001"),
+        ]);
+
+        let app = app_from_labels("grafana", &labels).unwrap();
+        assert_eq!(app.name, "Grafana");
+        assert!(app.description.is_none());
+        assert!(app.icon_url.is_none());
+        assert!(app.category.is_none());
+    }
+
+    #[test]
+    fn test_app_from_labels_not_enabled() {
+        let disabled = labels(&[
+            ("homarr.enable", "false"),
+            ("homarr.name", "Grafana"),
+            ("homarr.url", "http://localhost:3001"),
+        ]);
+        assert!(app_from_labels("grafana", &disabled).is_none());
+
+        let missing = labels(&[
+            ("homarr.name", "Grafana"),
+            ("homarr.url", "http://localhost:3001"),
+        ]);
+        assert!(app_from_labels("grafana", &missing).is_none());
+    }
+
+    #[test]
+    fn test_app_from_labels_missing_required() {
+        let no_name = labels(&[
+            ("homarr.enable", "true"),
+            ("homarr.url", "http://localhost:3001"),
+        ]);
+        assert!(app_from_labels("grafana", &no_name).is_none());
+
+        let no_url = labels(&[("homarr.enable", "true"), ("homarr.name", "Grafana")]);
+        assert!(app_from_labels("grafana", &no_url).is_none());
+    }
+
+    #[test]
+    fn test_app_from_labels_invalid_url() {
+        let labels = labels(&[
+            ("homarr.enable", "true"),
+            ("homarr.name", "Grafana"),
+            ("homarr.url", "not-a-valid-url"),
+        ]);
+        assert!(app_from_labels("grafana", &labels).is_none());
+    }
+
+    #[test]
+    fn test_app_from_labels_blank_optional_ignored() {
+        let labels = labels(&[
+            ("homarr.enable", "true"),
+            ("homarr.name", "Grafana"),
+            ("homarr.url", "http://localhost:3001"),
+            ("homarr.category", "  "),
+        ]);
+
+        let app = app_from_labels("grafana", &labels).unwrap();
+        assert!(app.category.is_none());
+    }
+}
@@ -10,9 +10,15 @@ pub enum AdapterError {
     #[error("Homarr API error: {0}")]
     HomarrApi(String),
 
+    #[error("Homarr rejected the API key: {0}")]
+    Unauthorized(String),
+
     #[error("State file error: {0}")]
     State(String),
 
+    #[error("State file version {found} is newer than the supported version {supported}")]
+    StateVersion { found: String, supported: String },
+
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 
@@ -30,6 +36,9 @@ pub enum AdapterError {
 
     #[error("Docker error: {0}")]
     Docker(#[from] bollard::errors::Error),
+
+    #[error("File watch error: {0}")]
+    Watch(#[from] notify::Error),
 }
 
 pub type Result<T> = std::result::Result<T, AdapterError>;
@@ -0,0 +1,502 @@
+//! Health and readiness endpoint for watch mode
+//!
+//! When `health_listen` is set, watch mode serves a small HTTP endpoint on
+//! localhost or a Unix socket so that fleet tooling can check the adapter
+//! without scraping logs:
+//!
+//! - `GET /health`: always 200 while the daemon runs, with the JSON report
+//! - `GET /ready`: 200 if the last sync succeeded and Docker, Homarr and the
+//!   API key are all fine, 503 otherwise, with the same JSON report
+//! - `GET /metrics`: Prometheus metrics (see `metrics.rs`)
+//!
+//! The report is updated by the watch loop after each sync; requests never
+//! call Homarr or Docker themselves.
+
+use bollard::Docker;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::net::SocketAddr;
+use std::os::unix::fs::FileTypeExt;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::{TcpListener, UnixListener};
+
+use crate::apikey;
+use crate::config::Config;
+use crate::error::{AdapterError, Result};
+use crate::homarr::HomarrClient;
+use crate::metrics;
+use crate::retry::RequestPolicy;
+use crate::state::State;
+
+/// Largest request head accepted, in bytes
+const MAX_REQUEST_SIZE: usize = 8 * 1024;
+
+/// Time allowed for a client to send its request
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Time allowed for the Docker daemon to answer a ping
+const DOCKER_PING_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Health report shared between the watch loop and the listener
+pub type SharedHealth = Arc<Mutex<Health>>;
+
+/// What the watch loop knows about the adapter's health
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct Health {
+    /// End of the last successful sync
+    pub last_success: Option<DateTime<Utc>>,
+
+    /// Error of the last failed sync
+    pub last_error: Option<String>,
+
+    /// End of the last failed sync
+    pub last_error_at: Option<DateTime<Utc>>,
+
+    /// Whether the Docker daemon answered the last check
+    pub docker_connected: bool,
+
+    /// Whether Homarr answered the last check
+    pub homarr_reachable: bool,
+
+    /// Whether Homarr accepted the stored API key (None if unknown)
+    pub api_key_valid: Option<bool>,
+
+    /// When Docker and Homarr were last checked
+    pub checked_at: Option<DateTime<Utc>>,
+}
+
+impl Health {
+    /// Record the outcome of a sync
+    pub fn record_sync(&mut self, result: &Result<()>) {
+        let now = Utc::now();
+        match result {
+            Ok(()) => self.last_success = Some(now),
+            Err(e) => {
+                self.last_error = Some(e.to_string());
+                self.last_error_at = Some(now);
+            }
+        }
+    }
+
+    /// Check if the adapter is fully working
+    pub fn is_ready(&self) -> bool {
+        let last_sync_ok = match (self.last_success, self.last_error_at) {
+            (Some(success), Some(error)) => success > error,
+            (Some(_), None) => true,
+            (None, _) => false,
+        };
+
+        last_sync_ok
+            && self.docker_connected
+            && self.homarr_reachable
+            && self.api_key_valid == Some(true)
+    }
+
+    /// JSON report served by the endpoint
+    pub fn report(&self) -> serde_json::Value {
+        let mut report = serde_json::to_value(self).unwrap_or_default();
+        report["ready"] = self.is_ready().into();
+        report
+    }
+}
+
+/// Check Docker and Homarr and update the health report
+///
+/// Uses the API key stored in state, so no key is rotated here. The Docker
+/// ping and the Homarr request each time out after a few seconds, so a hung
+/// daemon can't stall the watch loop.
+pub async fn refresh(health: &SharedHealth, config: &Config, docker: &Docker) {
+    let docker_connected = matches!(
+        tokio::time::timeout(DOCKER_PING_TIMEOUT, docker.ping()).await,
+        Ok(Ok(_))
+    );
+
+    let api_key = State::load_readonly(&config.state_file)
+        .ok()
+        .and_then(|state| apikey::stored_key(&state).ok().flatten());
+    let (homarr_reachable, api_key_valid) =
+        match HomarrClient::new(&config.homarr_url, RequestPolicy::probe(config)) {
+            Ok(mut client) => match api_key {
+                Some(api_key) => {
+                    client.set_api_key(api_key);
+                    match client.check_api_key().await {
+                        Ok(valid) => (true, Some(valid)),
+                        Err(e) => (homarr_answered(&e), None),
+                    }
+                }
+                // Without a key, ask Homarr something that needs none
+                None => match client.get_onboarding_step().await {
+                    Ok(_) => (true, Some(false)),
+                    Err(e) => (homarr_answered(&e), Some(false)),
+                },
+            },
+            Err(_) => (false, None),
+        };
+
+    let mut health = health.lock().unwrap();
+    health.docker_connected = docker_connected;
+    health.homarr_reachable = homarr_reachable;
+    health.api_key_valid = api_key_valid;
+    health.checked_at = Some(Utc::now());
+}
+
+/// Check if Homarr answered a health check request that failed
+fn homarr_answered(e: &AdapterError) -> bool {
+    tracing::debug!("Homarr health check failed: {}", e);
+    !matches!(e, AdapterError::Http(_))
+}
+
+/// Address of the health listener
+#[derive(Debug, Clone, PartialEq)]
+pub enum ListenAddr {
+    /// Loopback TCP address, e.g. `127.0.0.1:8787`
+    Tcp(SocketAddr),
+
+    /// Unix socket path, e.g. `unix:/run/homarr-container-adapter/health.sock`
+    Unix(PathBuf),
+}
+
+impl ListenAddr {
+    /// Parse a `health_listen` value
+    ///
+    /// TCP addresses must be loopback: the endpoint has no authentication.
+    pub fn parse(value: &str) -> Result<Self> {
+        if let Some(path) = value.strip_prefix("unix:") {
+            return Ok(Self::Unix(PathBuf::from(path)));
+        }
+        if value.starts_with('/') {
+            return Ok(Self::Unix(PathBuf::from(value)));
+        }
+
+        let addr: SocketAddr = value.parse().map_err(|e| {
+            AdapterError::Config(format!("Invalid health_listen '{}': {}", value, e))
+        })?;
+        if !addr.ip().is_loopback() {
+            return Err(AdapterError::Config(format!(
+                "health_listen must be a loopback address or a Unix socket, got '{}'",
+                value
+            )));
+        }
+
+        Ok(Self::Tcp(addr))
+    }
+}
+
+/// A bound health listener
+pub enum Listener {
+    Tcp(TcpListener),
+    Unix(UnixListener),
+}
+
+impl Listener {
+    /// Bind the listener, replacing a stale Unix socket file
+    ///
+    /// Anything other than a socket at the path is left alone and reported as
+    /// a config error.
+    pub async fn bind(addr: &ListenAddr) -> Result<Self> {
+        match addr {
+            ListenAddr::Tcp(addr) => Ok(Self::Tcp(TcpListener::bind(addr).await?)),
+            ListenAddr::Unix(path) => {
+                match std::fs::symlink_metadata(path) {
+                    Ok(metadata) if metadata.file_type().is_socket() => std::fs::remove_file(path)?,
+                    Ok(_) => {
+                        return Err(AdapterError::Config(format!(
+                            "health_listen path {} exists and is not a socket",
+                            path.display()
+                        )))
+                    }
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                    Err(e) => return Err(e.into()),
+                }
+                Ok(Self::Unix(UnixListener::bind(path)?))
+            }
+        }
+    }
+
+    /// Accept connections and answer health requests until the process exits
+    pub async fn serve(self, health: SharedHealth) {
+        loop {
+            let accepted = match &self {
+                Self::Tcp(listener) => listener.accept().await.map(|(stream, _)| {
+                    tokio::spawn(handle_connection(stream, health.clone()));
+                }),
+                Self::Unix(listener) => listener.accept().await.map(|(stream, _)| {
+                    tokio::spawn(handle_connection(stream, health.clone()));
+                }),
+            };
+            if let Err(e) = accepted {
+                tracing::warn!("Health listener failed to accept a connection: {}", e);
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+        }
+    }
+}
+
+/// An HTTP response from the endpoint
+#[derive(Debug)]
+struct Response {
+    status: u16,
+    content_type: &'static str,
+    body: String,
+}
+
+impl Response {
+    fn json(status: u16, value: &serde_json::Value) -> Self {
+        Self {
+            status,
+            content_type: "application/json",
+            body: format!("{}\n", value),
+        }
+    }
+
+    fn error(status: u16, message: &str) -> Self {
+        Self::json(status, &serde_json::json!({ "error": message }))
+    }
+
+    fn reason(&self) -> &'static str {
+        match self.status {
+            200 => "OK",
+            400 => "Bad Request",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            503 => "Service Unavailable",
+            _ => "",
+        }
+    }
+}
+
+/// Read one request, answer it and close the connection
+async fn handle_connection<S: AsyncRead + AsyncWrite + Unpin>(mut stream: S, health: SharedHealth) {
+    let response = match tokio::time::timeout(REQUEST_TIMEOUT, read_request_line(&mut stream)).await
+    {
+        Ok(Some(line)) => {
+            let mut parts = line.split_whitespace();
+            let method = parts.next().unwrap_or_default();
+            let target = parts.next().unwrap_or_default();
+            route(method, target, &health)
+        }
+        Ok(None) => Response::error(400, "bad request"),
+        Err(_) => return,
+    };
+
+    let head = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        response.status,
+        response.reason(),
+        response.content_type,
+        response.body.len()
+    );
+    let _ = stream.write_all(head.as_bytes()).await;
+    let _ = stream.write_all(response.body.as_bytes()).await;
+    let _ = stream.shutdown().await;
+}
+
+/// Read the request head and return its first line
+async fn read_request_line<S: AsyncRead + Unpin>(stream: &mut S) -> Option<String> {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 1024];
+
+    while !buf.windows(4).any(|w| w == b"\r\n\r\n") {
+        let n = stream.read(&mut chunk).await.ok()?;
+        if n == 0 || buf.len() + n > MAX_REQUEST_SIZE {
+            return None;
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    }
+
+    let head = String::from_utf8_lossy(&buf);
+    head.lines().next().map(str::to_string)
+}
+
+/// Answer a request
+fn route(method: &str, target: &str, health: &SharedHealth) -> Response {
+    let path = target.split('?').next().unwrap_or_default();
+
+    if !matches!(path, "/health" | "/ready" | "/metrics") {
+        return Response::error(404, "not found");
+    }
+    if method != "GET" {
+        return Response::error(405, "method not allowed");
+    }
+
+    if path == "/metrics" {
+        return Response {
+            status: 200,
+            content_type: metrics::CONTENT_TYPE,
+            body: metrics::render(),
+        };
+    }
+
+    let health = health.lock().unwrap();
+    let status = if path == "/ready" && !health.is_ready() {
+        503
+    } else {
+        200
+    };
+    Response::json(status, &health.report())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+    use tokio::net::UnixStream;
+
+    fn ready_health() -> Health {
+        Health {
+            last_success: Some(Utc::now()),
+            docker_connected: true,
+            homarr_reachable: true,
+            api_key_valid: Some(true),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_listen_addr_parse() {
+        assert_eq!(
+            ListenAddr::parse("127.0.0.1:8787").unwrap(),
+            ListenAddr::Tcp("127.0.0.1:8787".parse().unwrap())
+        );
+        assert_eq!(
+            ListenAddr::parse("[::1]:8787").unwrap(),
+            ListenAddr::Tcp("[::1]:8787".parse().unwrap())
+        );
+        assert_eq!(
+            ListenAddr::parse("unix:/run/adapter/health.sock").unwrap(),
+            ListenAddr::Unix(PathBuf::from("/run/adapter/health.sock"))
+        );
+        assert_eq!(
+            ListenAddr::parse("/run/adapter/health.sock").unwrap(),
+            ListenAddr::Unix(PathBuf::from("/run/adapter/health.sock"))
+        );
+        assert!(ListenAddr::parse("0.0.0.0:8787").is_err());
+        assert!(ListenAddr::parse("localhost").is_err());
+    }
+
+    #[test]
+    fn test_is_ready() {
+        assert!(!Health::default().is_ready());
+        assert!(ready_health().is_ready());
+
+        let mut health = ready_health();
+        health.api_key_valid = None;
+        assert!(!health.is_ready());
+
+        let mut health = ready_health();
+        health.record_sync(&Err(AdapterError::State("broken".to_string())));
+        assert!(!health.is_ready());
+        assert_eq!(
+            health.last_error.as_deref(),
+            Some("State file error: broken")
+        );
+
+        health.record_sync(&Ok(()));
+        assert!(health.is_ready());
+    }
+
+    #[test]
+    fn test_route() {
+        let health: SharedHealth = Arc::new(Mutex::new(Health::default()));
+
+        let response = route("GET", "/health", &health);
+        assert_eq!(response.status, 200);
+        let report: serde_json::Value = serde_json::from_str(&response.body).unwrap();
+        assert_eq!(report["ready"], serde_json::json!(false));
+        assert_eq!(report["docker_connected"], serde_json::json!(false));
+
+        assert_eq!(route("GET", "/ready", &health).status, 503);
+        *health.lock().unwrap() = ready_health();
+        assert_eq!(route("GET", "/ready?verbose=1", &health).status, 200);
+
+        let response = route("GET", "/metrics", &health);
+        assert_eq!(response.status, 200);
+        assert_eq!(response.content_type, metrics::CONTENT_TYPE);
+
+        assert_eq!(route("POST", "/ready", &health).status, 405);
+        assert_eq!(route("GET", "/", &health).status, 404);
+    }
+
+    #[tokio::test]
+    async fn test_serve_unix_socket() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("health.sock");
+        // A stale socket file from a previous run is replaced
+        drop(std::os::unix::net::UnixListener::bind(&path).unwrap());
+        assert!(path.exists());
+
+        let listener = Listener::bind(&ListenAddr::Unix(path.clone()))
+            .await
+            .unwrap();
+        let health: SharedHealth = Arc::new(Mutex::new(ready_health()));
+        tokio::spawn(listener.serve(health));
+
+        let mut stream = UnixStream::connect(&path).await.unwrap();
+        stream
+            .write_all(b"GET /ready HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("Content-Type: application/json"));
+        assert!(response.contains("\"ready\":true"));
+    }
+
+    #[tokio::test]
+    async fn test_refresh_without_api_key_probes_homarr() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut buf = [0u8; 4096];
+                let _ = stream.read(&mut buf).await;
+                let body = r#"{"result":{"data":{"json":{"current":"start","previous":null}}}}"#;
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+
+        let temp_dir = TempDir::new().unwrap();
+        let config = Config {
+            homarr_url: base_url,
+            state_file: temp_dir.path().join("state.json").display().to_string(),
+            ..Default::default()
+        };
+        // Not a socket, so pings fail
+        let docker_socket = temp_dir.path().join("docker.sock");
+        std::fs::write(&docker_socket, "").unwrap();
+        let docker = crate::docker::connect(&docker_socket.display().to_string()).unwrap();
+        let health: SharedHealth = Default::default();
+
+        refresh(&health, &config, &docker).await;
+
+        let health = health.lock().unwrap();
+        assert!(health.homarr_reachable);
+        assert_eq!(health.api_key_valid, Some(false));
+        assert!(!health.docker_connected);
+    }
+
+    #[tokio::test]
+    async fn test_bind_refuses_to_replace_other_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("health.sock");
+        std::fs::write(&path, "data").unwrap();
+
+        let result = Listener::bind(&ListenAddr::Unix(path.clone())).await;
+
+        assert!(matches!(result, Err(AdapterError::Config(_))));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "data");
+    }
+}
@@ -7,7 +7,9 @@ use std::sync::Arc;
 
 use crate::branding::BrandingConfig;
 use crate::error::{AdapterError, Result};
+use crate::metrics;
 use crate::registry::AppDefinition;
+use crate::retry::{self, RequestPolicy};
 
 /// Homarr API client
 pub struct HomarrClient {
@@ -15,6 +17,7 @@ pub struct HomarrClient {
     base_url: String,
     /// API key for authentication (format: "{id}.{token}")
     api_key: Option<String>,
+    policy: RequestPolicy,
 }
 
 #[derive(Debug, Deserialize)]
@@ -45,33 +48,59 @@ struct CsrfResponse {
     csrf_token: String,
 }
 
-#[derive(Debug, Deserialize)]
-#[allow(dead_code)]
-struct BoardResponse {
-    id: String,
-    name: String,
-    sections: Vec<Section>,
-    layouts: Vec<Layout>,
+/// Board data from getBoardByName endpoint
+#[derive(Debug, Deserialize, Clone)]
+pub struct Board {
+    pub id: String,
+    pub name: String,
+    pub sections: Vec<Section>,
+    pub layouts: Vec<Layout>,
+    /// Board items, kept as raw JSON so they can be saved back unchanged
+    #[serde(default)]
+    pub items: Vec<serde_json::Value>,
 }
 
+/// Board section (empty, category or dynamic)
 #[derive(Debug, Deserialize, Serialize, Clone)]
-struct Section {
-    id: String,
-    kind: String,
+pub struct Section {
+    pub id: String,
+    pub kind: String,
     #[serde(rename = "yOffset")]
-    y_offset: i32,
+    pub y_offset: i32,
     #[serde(rename = "xOffset")]
-    x_offset: i32,
+    pub x_offset: i32,
+    /// Category name (only for kind "category")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Remaining fields (e.g. collapsed state) passed back unchanged on save
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+const SECTION_KIND_EMPTY: &str = "empty";
+const SECTION_KIND_CATEGORY: &str = "category";
+
+impl Section {
+    /// Check if this is a category section with the given name (case-insensitive)
+    pub fn is_category(&self, name: &str) -> bool {
+        self.kind == SECTION_KIND_CATEGORY
+            && self
+                .name
+                .as_deref()
+                .map(|n| n.eq_ignore_ascii_case(name))
+                .unwrap_or(false)
+    }
 }
 
+/// Board layout (one per breakpoint)
 #[derive(Debug, Deserialize, Clone)]
 #[allow(dead_code)]
-struct Layout {
-    id: String,
-    name: String,
+pub struct Layout {
+    pub id: String,
+    pub name: String,
     #[serde(rename = "columnCount")]
-    column_count: i32,
-    breakpoint: i32,
+    pub column_count: i32,
+    pub breakpoint: i32,
 }
 
 #[derive(Debug, Deserialize)]
@@ -179,18 +208,22 @@ fn derive_ping_url(app_url: &str) -> Option<String> {
     }
 }
 
-/// Simple hash function for generating unique IDs from URLs
-fn string_hash(s: &str) -> u64 {
-    use std::collections::hash_map::DefaultHasher;
-    use std::hash::{Hash, Hasher};
-    let mut hasher = DefaultHasher::new();
-    s.hash(&mut hasher);
-    hasher.finish()
+/// 64-bit FNV-1a hash, for IDs that must not change between releases
+///
+/// Unlike `DefaultHasher`, whose output may differ between Rust versions, the
+/// algorithm and its constants are fixed (see
+/// <http://www.isthe.com/chongo/tech/comp/fnv/>).
+fn fnv1a_hash(s: &str) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+    s.bytes().fold(OFFSET_BASIS, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(PRIME)
+    })
 }
 
 /// Check if a board already has an item for a given app ID.
 /// Used to prevent duplicate board items when the same app is synced multiple times.
-fn board_has_app(items: &[serde_json::Value], app_id: &str) -> bool {
+pub fn board_has_app(items: &[serde_json::Value], app_id: &str) -> bool {
     items.iter().any(|item| {
         item.get("options")
             .and_then(|o| o.get("appId"))
@@ -199,6 +232,188 @@ fn board_has_app(items: &[serde_json::Value], app_id: &str) -> bool {
     })
 }
 
+/// Generate a stable section ID for a registry category
+fn category_section_id(category: &str) -> String {
+    let slug: String = category
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    format!("registry-category-{}", slug)
+}
+
+/// Make sure the board has one category section per registry category
+///
+/// `categories` holds each category name with the lowest priority of the apps
+/// in it. Existing category sections with a matching name are reused (so
+/// user-created categories are adopted). Missing ones are appended at the end
+/// of the board, each followed by an empty section as Homarr does.
+///
+/// Registry category sections are then ordered by priority among themselves:
+/// they swap into the vertical slots already occupied by registry categories,
+/// so sections that don't belong to the registry keep their positions.
+///
+/// Returns true if the sections were changed.
+pub fn arrange_category_sections(sections: &mut Vec<Section>, categories: &[(String, u8)]) -> bool {
+    let mut changed = false;
+
+    for (category, _) in categories {
+        if sections.iter().any(|s| s.is_category(category)) {
+            continue;
+        }
+
+        let next_y = sections.iter().map(|s| s.y_offset + 1).max().unwrap_or(0);
+        let id = category_section_id(category);
+        let mut extra = serde_json::Map::new();
+        extra.insert("collapsed".to_string(), json!(false));
+        sections.push(Section {
+            id: id.clone(),
+            kind: SECTION_KIND_CATEGORY.to_string(),
+            y_offset: next_y,
+            x_offset: 0,
+            name: Some(category.clone()),
+            extra,
+        });
+        sections.push(Section {
+            id: format!("{}-after", id),
+            kind: SECTION_KIND_EMPTY.to_string(),
+            y_offset: next_y + 1,
+            x_offset: 0,
+            name: None,
+            extra: serde_json::Map::new(),
+        });
+        changed = true;
+    }
+
+    // Indices of registry category sections, and their current vertical slots
+    let mut managed: Vec<(usize, u8)> = sections
+        .iter()
+        .enumerate()
+        .filter_map(|(i, section)| {
+            categories
+                .iter()
+                .find(|(name, _)| section.is_category(name))
+                .map(|(_, priority)| (i, *priority))
+        })
+        .collect();
+    let mut slots: Vec<i32> = managed.iter().map(|(i, _)| sections[*i].y_offset).collect();
+    slots.sort_unstable();
+
+    managed.sort_by(|(a_idx, a_prio), (b_idx, b_prio)| {
+        a_prio
+            .cmp(b_prio)
+            .then_with(|| sections[*a_idx].name.cmp(&sections[*b_idx].name))
+    });
+
+    for ((idx, _), y_offset) in managed.into_iter().zip(slots) {
+        if sections[idx].y_offset != y_offset {
+            sections[idx].y_offset = y_offset;
+            changed = true;
+        }
+    }
+
+    changed
+}
+
+/// Pick the section an app's tile belongs in
+///
+/// Apps with a category go into the matching category section. Others (or if
+/// the category section is missing) go into the topmost empty section.
+pub fn section_for_app<'a>(sections: &'a [Section], category: Option<&str>) -> Option<&'a Section> {
+    if let Some(category) = category {
+        if let Some(section) = sections.iter().find(|s| s.is_category(category)) {
+            return Some(section);
+        }
+    }
+
+    sections
+        .iter()
+        .filter(|s| s.kind == SECTION_KIND_EMPTY)
+        .min_by_key(|s| s.y_offset)
+        .or_else(|| sections.first())
+}
+
+/// Collect the item IDs of a board's app items with the app ID each refers to
+pub fn board_app_items(items: &[serde_json::Value]) -> Vec<(&str, &str)> {
+    items
+        .iter()
+        .filter_map(|item| {
+            let item_id = item.get("id").and_then(|i| i.as_str())?;
+            let app_id = item
+                .get("options")
+                .and_then(|o| o.get("appId"))
+                .and_then(|a| a.as_str())?;
+            Some((item_id, app_id))
+        })
+        .collect()
+}
+
+/// Generate a unique ID for a registry app's board item
+///
+/// Uses the container name if available, otherwise an FNV-1a hash of the URL.
+pub fn board_item_id(app: &AppDefinition) -> String {
+    if let Some(container) = app.container_name() {
+        format!("registry-{}", container)
+    } else {
+        format!("registry-{:x}", fnv1a_hash(&app.url))
+    }
+}
+
+/// Board item ID earlier versions generated for an app without a container
+///
+/// Those hashed the URL with `DefaultHasher`, so only tiles created by a build
+/// with the same Rust version are recognised. Sync records a matching tile's
+/// ID in state, after which the hasher no longer matters.
+pub fn legacy_board_item_id(app: &AppDefinition) -> Option<String> {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+
+    if app.container_name().is_some() {
+        return None;
+    }
+    let mut hasher = DefaultHasher::new();
+    app.url.hash(&mut hasher);
+    Some(format!("registry-{:x}", hasher.finish()))
+}
+
+/// Build a board item of kind "app" for saving with `board.saveBoard`
+#[allow(clippy::too_many_arguments)]
+pub fn app_board_item(
+    item_id: &str,
+    app_id: &str,
+    layout_id: &str,
+    section_id: &str,
+    x_offset: i32,
+    y_offset: i32,
+    width: i32,
+    height: i32,
+) -> serde_json::Value {
+    json!({
+        "id": item_id,
+        "kind": "app",
+        "options": {
+            "appId": app_id
+        },
+        "layouts": [{
+            "layoutId": layout_id,
+            "sectionId": section_id,
+            "width": width,
+            "height": height,
+            "xOffset": x_offset,
+            "yOffset": y_offset
+        }],
+        "integrationIds": [],
+        "advancedOptions": {
+            "customCssClasses": []
+        }
+    })
+}
+
+/// Icon URL sent to Homarr for a registry app
+pub fn app_icon_url(app: &AppDefinition) -> String {
+    transform_icon_url(app.icon_url.as_deref().unwrap_or(DEFAULT_ICON))
+}
+
 /// Transform icon paths to relative URLs for Homarr.
 ///
 /// Icons are served by Homarr's nginx from /icons/ which maps to /usr/share/pixmaps.
@@ -207,7 +422,7 @@ fn board_has_app(items: &[serde_json::Value], app_id: &str) -> bool {
 /// - HTTP/HTTPS URLs → unchanged
 /// - `/icons/*` paths → unchanged
 /// - Everything else → `/icons/docker.svg` (fallback)
-fn transform_icon_url(icon_path: &str) -> String {
+pub fn transform_icon_url(icon_path: &str) -> String {
     const PIXMAPS_PREFIX: &str = "/usr/share/pixmaps/";
 
     if icon_path.is_empty() {
@@ -236,24 +451,40 @@ fn transform_icon_url(icon_path: &str) -> String {
     DEFAULT_ICON.to_string()
 }
 
+/// Error for a failed API request
+///
+/// 401 means Homarr did not accept the API key, which callers may recover
+/// from by authenticating again, so it gets its own error type.
+fn api_error(status: reqwest::StatusCode, message: String) -> AdapterError {
+    if status == reqwest::StatusCode::UNAUTHORIZED {
+        AdapterError::Unauthorized(message)
+    } else {
+        AdapterError::HomarrApi(message)
+    }
+}
+
 impl HomarrClient {
     /// Create a new Homarr client
     ///
     /// # Arguments
     /// * `base_url` - The Homarr API base URL (e.g., "http://localhost:80")
-    pub fn new(base_url: &str) -> Result<Self> {
+    /// * `policy` - Timeouts and retries for requests
+    pub fn new(base_url: &str, policy: RequestPolicy) -> Result<Self> {
         let jar = Arc::new(Jar::default());
         let client = Client::builder()
             .cookie_store(true)
             .cookie_provider(jar)
             // Accept self-signed certificates (required for local SSL configurations)
             .danger_accept_invalid_certs(true)
+            .connect_timeout(policy.connect_timeout)
+            .timeout(policy.timeout)
             .build()?;
 
         Ok(Self {
             client,
             base_url: base_url.trim_end_matches('/').to_string(),
             api_key: None,
+            policy,
         })
     }
 
@@ -264,39 +495,115 @@ impl HomarrClient {
         self.api_key = Some(api_key);
     }
 
-    /// Make an authenticated GET request
+    /// Make an authenticated GET request, retried on transient failures
     async fn get(&self, url: &str) -> reqwest::Result<reqwest::Response> {
-        let mut request = self.client.get(url);
-        if let Some(ref api_key) = self.api_key {
-            request = request.header("ApiKey", api_key);
+        self.send(url, true, || self.client.get(url)).await
+    }
+
+    /// Send an authenticated request
+    ///
+    /// Idempotent requests are retried with backoff after connection errors,
+    /// timeouts and 5xx responses, up to the policy's retry limit.
+    async fn send(
+        &self,
+        url: &str,
+        idempotent: bool,
+        build: impl Fn() -> reqwest::RequestBuilder,
+    ) -> reqwest::Result<reqwest::Response> {
+        let max_retries = if idempotent {
+            self.policy.max_retries
+        } else {
+            0
+        };
+        let procedure = metrics::procedure(url);
+
+        let mut retries = 0;
+        loop {
+            let mut request = build();
+            if let Some(ref api_key) = self.api_key {
+                request = request.header("ApiKey", api_key);
+            }
+            let result = Self::record(url, request.send().await);
+
+            if !retry::is_transient(&result) {
+                if retries > 0 {
+                    tracing::info!(
+                        "Homarr request {} succeeded after {} retries",
+                        procedure,
+                        retries
+                    );
+                }
+                return result;
+            }
+            let reason = match &result {
+                Ok(response) => response.status().to_string(),
+                Err(e) => e.to_string(),
+            };
+            if retries >= max_retries {
+                if retries > 0 {
+                    tracing::warn!(
+                        "Homarr request {} failed after {} retries: {}",
+                        procedure,
+                        retries,
+                        reason
+                    );
+                }
+                return result;
+            }
+
+            retries += 1;
+            let delay = self.policy.backoff.delay(retries);
+            tracing::warn!(
+                "Homarr request {} failed ({}), retry {}/{} in {:?}",
+                procedure,
+                reason,
+                retries,
+                max_retries,
+                delay
+            );
+            tokio::time::sleep(delay).await;
         }
-        request.send().await
     }
 
-    /// Make an authenticated POST request with JSON body
+    /// Record a request's outcome in the metrics
+    fn record(
+        url: &str,
+        result: reqwest::Result<reqwest::Response>,
+    ) -> reqwest::Result<reqwest::Response> {
+        let status = result.as_ref().ok().map(|r| r.status().as_u16());
+        metrics::record_homarr_request(url, status);
+        result
+    }
+
+    /// Make an authenticated POST request with JSON body (sent once)
     async fn post_json<T: Serialize + ?Sized>(
         &self,
         url: &str,
         body: &T,
     ) -> reqwest::Result<reqwest::Response> {
-        let mut request = self.client.post(url).json(body);
-        if let Some(ref api_key) = self.api_key {
-            request = request.header("ApiKey", api_key);
-        }
-        request.send().await
+        self.send(url, false, || self.client.post(url).json(body))
+            .await
+    }
+
+    /// Make an authenticated POST request with JSON body for a procedure that
+    /// can safely run twice, retried on transient failures
+    async fn post_json_idempotent<T: Serialize + ?Sized>(
+        &self,
+        url: &str,
+        body: &T,
+    ) -> reqwest::Result<reqwest::Response> {
+        self.send(url, true, || self.client.post(url).json(body))
+            .await
     }
 
-    /// Make an authenticated POST request with form data
+    /// Make an authenticated POST request with form data (sent once)
     async fn post_form<T: Serialize + ?Sized>(
         &self,
         url: &str,
         form: &T,
     ) -> reqwest::Result<reqwest::Response> {
-        let mut request = self.client.post(url).form(form);
-        if let Some(ref api_key) = self.api_key {
-            request = request.header("ApiKey", api_key);
-        }
-        request.send().await
+        self.send(url, false, || self.client.post(url).form(form))
+            .await
     }
 
     /// Get current onboarding step
@@ -513,7 +820,7 @@ impl HomarrClient {
     }
 
     /// Get board by name
-    async fn get_board_by_name(&self, name: &str) -> Result<BoardResponse> {
+    async fn get_board_by_name(&self, name: &str) -> Result<Board> {
         let url = format!(
             "{}/api/trpc/board.getBoardByName?input={}",
             self.base_url,
@@ -523,10 +830,10 @@ impl HomarrClient {
         let response = self.get(&url).await?;
 
         if !response.status().is_success() {
-            return Err(AdapterError::HomarrApi("Board not found".to_string()));
+            return Err(api_error(response.status(), "Board not found".to_string()));
         }
 
-        let trpc_response: TrpcResponse<BoardResponse> = response.json().await?;
+        let trpc_response: TrpcResponse<Board> = response.json().await?;
         Ok(trpc_response.result.data.json)
     }
 
@@ -602,6 +909,29 @@ impl HomarrClient {
         self.api_key.is_some()
     }
 
+    /// Check if the configured API key is accepted by Homarr
+    ///
+    /// Lists API keys, which requires an authenticated admin. Returns `false`
+    /// if Homarr rejects the key (401/403) and an error if Homarr is
+    /// unreachable or fails otherwise.
+    pub async fn check_api_key(&self) -> Result<bool> {
+        let url = format!("{}/api/trpc/apiKeys.getAll", self.base_url);
+        let response = self.get(&url).await?;
+        let status = response.status();
+
+        if status == reqwest::StatusCode::UNAUTHORIZED || status == reqwest::StatusCode::FORBIDDEN {
+            return Ok(false);
+        }
+        if !status.is_success() {
+            return Err(AdapterError::HomarrApi(format!(
+                "Failed to check API key ({})",
+                status
+            )));
+        }
+
+        Ok(true)
+    }
+
     /// Create a new API key
     ///
     /// Requires authentication (API key must be set).
@@ -615,10 +945,10 @@ impl HomarrClient {
         if !response.status().is_success() {
             let status = response.status();
             let text = response.text().await.unwrap_or_default();
-            return Err(AdapterError::HomarrApi(format!(
-                "Failed to create API key ({}): {}",
-                status, text
-            )));
+            return Err(api_error(
+                status,
+                format!("Failed to create API key ({}): {}", status, text),
+            ));
         }
 
         let trpc_response: TrpcResponse<CreateApiKeyResponse> = response.json().await?;
@@ -632,15 +962,18 @@ impl HomarrClient {
         let url = format!("{}/api/trpc/apiKeys.delete", self.base_url);
         let payload = json!({"json": {"apiKeyId": api_key_id}});
 
-        let response = self.post_json(&url, &payload).await?;
+        let response = self.post_json_idempotent(&url, &payload).await?;
 
         if !response.status().is_success() {
             let status = response.status();
             let text = response.text().await.unwrap_or_default();
-            return Err(AdapterError::HomarrApi(format!(
-                "Failed to delete API key '{}' ({}): {}",
-                api_key_id, status, text
-            )));
+            return Err(api_error(
+                status,
+                format!(
+                    "Failed to delete API key '{}' ({}): {}",
+                    api_key_id, status, text
+                ),
+            ));
         }
 
         Ok(())
@@ -700,10 +1033,10 @@ impl HomarrClient {
         if !response.status().is_success() {
             let status = response.status();
             let text = response.text().await.unwrap_or_default();
-            return Err(AdapterError::HomarrApi(format!(
-                "Failed to fetch apps ({}): {}",
-                status, text
-            )));
+            return Err(api_error(
+                status,
+                format!("Failed to fetch apps ({}): {}", status, text),
+            ));
         }
 
         let trpc_response: TrpcResponse<Vec<SelectableApp>> = response.json().await?;
@@ -722,10 +1055,10 @@ impl HomarrClient {
         if !response.status().is_success() {
             let status = response.status();
             let text = response.text().await.unwrap_or_default();
-            return Err(AdapterError::HomarrApi(format!(
-                "Failed to fetch boards ({}): {}",
-                status, text
-            )));
+            return Err(api_error(
+                status,
+                format!("Failed to fetch boards ({}): {}", status, text),
+            ));
         }
 
         let trpc_response: TrpcResponse<Vec<BoardWithPermission>> = response.json().await?;
@@ -742,46 +1075,16 @@ impl HomarrClient {
     }
 
     /// Find an existing app by URL in a pre-fetched list
-    fn find_app_in_list<'a>(apps: &'a [SelectableApp], url: &str) -> Option<&'a SelectableApp> {
+    pub fn find_app_in_list<'a>(apps: &'a [SelectableApp], url: &str) -> Option<&'a SelectableApp> {
         apps.iter().find(|app| app.href.as_deref() == Some(url))
     }
 
-    /// Add a registry app to Homarr (or update if already exists)
+    /// Create a registry app in Homarr's global app registry
     ///
-    /// Registry apps can have explicit layout positioning and may not be Docker containers.
-    pub async fn add_registry_app(
-        &self,
-        app: &AppDefinition,
-        board_name: &str,
-        existing_apps: Option<&[SelectableApp]>,
-    ) -> Result<String> {
-        // Check if an app with the same URL already exists
-        let existing = match existing_apps {
-            Some(apps) => Self::find_app_in_list(apps, &app.url).cloned(),
-            None => match self.get_all_apps().await {
-                Ok(apps) => Self::find_app_in_list(&apps, &app.url).cloned(),
-                Err(e) => {
-                    tracing::warn!(
-                        "Failed to fetch existing apps for deduplication: {}. \
-                             Proceeding with create.",
-                        e
-                    );
-                    None
-                }
-            },
-        };
-
-        if let Some(existing_app) = existing {
-            // App already exists - update it and ensure it's on the board
-            self.update_registry_app(&existing_app.id, app).await?;
-            self.add_registry_app_to_board(&existing_app.id, app, board_name)
-                .await?;
-            return Ok(existing_app.id);
-        }
-
-        // Create new app in Homarr
+    /// Returns the new app ID.
+    pub async fn create_registry_app(&self, app: &AppDefinition) -> Result<String> {
         let url = format!("{}/api/trpc/app.create", self.base_url);
-        let icon_url = transform_icon_url(app.icon_url.as_deref().unwrap_or(DEFAULT_ICON));
+        let icon_url = app_icon_url(app);
 
         // Use explicit ping_url if provided, otherwise derive from URL
         // For external apps, don't set a ping URL (no health checks)
@@ -804,20 +1107,17 @@ impl HomarrClient {
         let response = self.post_json(&url, &payload).await?;
 
         if !response.status().is_success() {
+            let status = response.status();
             let text = response.text().await?;
-            return Err(AdapterError::HomarrApi(format!(
-                "Failed to create registry app '{}': {}",
-                app.name, text
-            )));
+            return Err(api_error(
+                status,
+                format!("Failed to create registry app '{}': {}", app.name, text),
+            ));
         }
 
         let app_response: TrpcResponse<CreateAppResponse> = response.json().await?;
         let app_id = app_response.result.data.json.app_id;
 
-        // Add to board with layout preferences
-        self.add_registry_app_to_board(&app_id, app, board_name)
-            .await?;
-
         tracing::info!(
             "Added registry app '{}' to Homarr (app_id: {})",
             app.name,
@@ -827,9 +1127,9 @@ impl HomarrClient {
     }
 
     /// Update an existing app with registry app data
-    async fn update_registry_app(&self, app_id: &str, app: &AppDefinition) -> Result<()> {
+    pub async fn update_registry_app(&self, app_id: &str, app: &AppDefinition) -> Result<()> {
         let url = format!("{}/api/trpc/app.update", self.base_url);
-        let icon_url = transform_icon_url(app.icon_url.as_deref().unwrap_or(DEFAULT_ICON));
+        let icon_url = app_icon_url(app);
 
         let ping_url = if app.is_external() {
             None
@@ -848,14 +1148,15 @@ impl HomarrClient {
             }
         });
 
-        let response = self.post_json(&url, &payload).await?;
+        let response = self.post_json_idempotent(&url, &payload).await?;
 
         if !response.status().is_success() {
+            let status = response.status();
             let text = response.text().await?;
-            return Err(AdapterError::HomarrApi(format!(
-                "Failed to update registry app '{}': {}",
-                app.name, text
-            )));
+            return Err(api_error(
+                status,
+                format!("Failed to update registry app '{}': {}", app.name, text),
+            ));
         }
 
         tracing::info!(
@@ -866,167 +1167,60 @@ impl HomarrClient {
         Ok(())
     }
 
-    /// Add a registry app to a board with layout preferences
-    async fn add_registry_app_to_board(
-        &self,
-        app_id: &str,
-        app: &AppDefinition,
-        board_name: &str,
-    ) -> Result<()> {
-        let board_items = self.get_board_items(board_name).await.unwrap_or_default();
-
-        // Check if this app is already on the board
-        if board_has_app(&board_items, app_id) {
-            tracing::info!(
-                "Registry app '{}' already on board '{}', skipping",
-                app.name,
-                board_name
-            );
-            return Ok(());
-        }
+    /// Delete an app from Homarr's global app registry
+    ///
+    /// Board items referencing the app are not removed by Homarr; the sync
+    /// removes them from each board and saves it with `save_board()` first.
+    pub async fn delete_app(&self, app_id: &str) -> Result<()> {
+        let url = format!("{}/api/trpc/app.delete", self.base_url);
+        let payload = json!({"json": {"id": app_id}});
 
-        let board = self.get_board_by_name(board_name).await?;
-
-        let section_id = board
-            .sections
-            .first()
-            .map(|s| s.id.clone())
-            .unwrap_or_default();
-        let layout_id = board
-            .layouts
-            .first()
-            .map(|l| l.id.clone())
-            .unwrap_or_default();
-
-        // Get layout preferences from registry
-        let layout = app.effective_layout();
-        let width = layout.width as i32;
-        let height = layout.height as i32;
-
-        // Use explicit position if provided, otherwise auto-position
-        let (x_offset, y_offset) = match (layout.x_offset, layout.y_offset) {
-            (Some(x), Some(y)) => (x as i32, y as i32),
-            _ => self.find_next_position(&board_items, 12), // 12 columns for new layout
-        };
+        let response = self.post_json_idempotent(&url, &payload).await?;
 
-        // Generate a unique ID for this board item
-        // Use container name if available, otherwise use a hash of the URL
-        let item_id = if let Some(container) = app.container_name() {
-            format!("registry-{}", container)
-        } else {
-            format!("registry-{:x}", string_hash(&app.url))
-        };
+        if !response.status().is_success() {
+            let status = response.status();
+            let text = response.text().await.unwrap_or_default();
+            return Err(api_error(
+                status,
+                format!("Failed to delete app '{}' ({}): {}", app_id, status, text),
+            ));
+        }
 
-        let url = format!("{}/api/trpc/board.saveBoard", self.base_url);
+        Ok(())
+    }
 
-        let mut items: Vec<serde_json::Value> = board_items;
-        items.push(json!({
-            "id": item_id,
-            "kind": "app",
-            "options": {
-                "appId": app_id
-            },
-            "layouts": [{
-                "layoutId": layout_id,
-                "sectionId": section_id,
-                "width": width,
-                "height": height,
-                "xOffset": x_offset,
-                "yOffset": y_offset
-            }],
-            "integrationIds": [],
-            "advancedOptions": {
-                "customCssClasses": []
-            }
-        }));
+    /// Get a board with its sections, layouts and items
+    pub async fn get_board(&self, name: &str) -> Result<Board> {
+        self.get_board_by_name(name).await
+    }
 
+    /// Save a board's sections and items
+    pub async fn save_board(&self, board: &Board) -> Result<()> {
+        let url = format!("{}/api/trpc/board.saveBoard", self.base_url);
         let payload = json!({
             "json": {
                 "id": board.id,
                 "sections": board.sections,
-                "items": items,
+                "items": board.items,
                 "integrations": []
             }
         });
 
-        self.post_json(&url, &payload).await?;
-
-        tracing::debug!(
-            "Added registry app '{}' to board at ({}, {}) size {}x{}",
-            app.name,
-            x_offset,
-            y_offset,
-            width,
-            height
-        );
-
-        Ok(())
-    }
-
-    /// Get board items
-    async fn get_board_items(&self, board_name: &str) -> Result<Vec<serde_json::Value>> {
-        let url = format!(
-            "{}/api/trpc/board.getBoardByName?input={}",
-            self.base_url,
-            urlencoding::encode(&format!("{{\"json\":{{\"name\":\"{}\"}}}}", board_name))
-        );
-
-        let response = self.get(&url).await?;
+        let response = self.post_json_idempotent(&url, &payload).await?;
 
         if !response.status().is_success() {
-            return Ok(vec![]);
-        }
-
-        // Parse the full board response to get items
-        let json: serde_json::Value = response.json().await?;
-        let items = json
-            .get("result")
-            .and_then(|r| r.get("data"))
-            .and_then(|d| d.get("json"))
-            .and_then(|j| j.get("items"))
-            .and_then(|i| i.as_array())
-            .cloned()
-            .unwrap_or_default();
-
-        Ok(items)
-    }
-
-    /// Find next available position on the board (simple left-to-right, top-to-bottom)
-    fn find_next_position(&self, items: &[serde_json::Value], column_count: i32) -> (i32, i32) {
-        let mut max_y = 0;
-        let mut positions_in_max_row: Vec<i32> = vec![];
-
-        for item in items {
-            if let Some(layouts) = item.get("layouts").and_then(|l| l.as_array()) {
-                for layout in layouts {
-                    let x = layout.get("xOffset").and_then(|x| x.as_i64()).unwrap_or(0) as i32;
-                    let y = layout.get("yOffset").and_then(|y| y.as_i64()).unwrap_or(0) as i32;
-                    let h = layout.get("height").and_then(|h| h.as_i64()).unwrap_or(1) as i32;
-
-                    let item_bottom = y + h;
-                    if item_bottom > max_y {
-                        max_y = item_bottom;
-                        positions_in_max_row.clear();
-                    }
-                    if y + h == max_y {
-                        let w = layout.get("width").and_then(|w| w.as_i64()).unwrap_or(1) as i32;
-                        for col in x..(x + w) {
-                            positions_in_max_row.push(col);
-                        }
-                    }
-                }
-            }
-        }
-
-        // Find first empty column in the last row, or start new row
-        for x in 0..column_count {
-            if !positions_in_max_row.contains(&x) {
-                return (x, max_y.saturating_sub(1).max(0));
-            }
+            let status = response.status();
+            let text = response.text().await.unwrap_or_default();
+            return Err(api_error(
+                status,
+                format!(
+                    "Failed to save board '{}' ({}): {}",
+                    board.name, status, text
+                ),
+            ));
         }
 
-        // All columns full, start new row
-        (0, max_y)
+        Ok(())
     }
 }
 
@@ -1035,192 +1229,97 @@ mod tests {
     use super::*;
     use serde_json::json;
 
-    fn create_test_client() -> HomarrClient {
-        HomarrClient::new("http://localhost:7575").unwrap()
-    }
-
     // HomarrClient creation tests
     #[test]
     fn test_client_new_valid_url() {
-        let client = HomarrClient::new("http://localhost:7575");
+        let client = HomarrClient::new("http://localhost:7575", RequestPolicy::default());
         assert!(client.is_ok());
     }
 
     #[test]
     fn test_client_new_strips_trailing_slash() {
-        let client = HomarrClient::new("http://localhost:7575/").unwrap();
+        let client = HomarrClient::new("http://localhost:7575/", RequestPolicy::default()).unwrap();
         assert_eq!(client.base_url, "http://localhost:7575");
     }
 
     #[test]
     fn test_client_new_preserves_path() {
-        let client = HomarrClient::new("http://localhost:7575/homarr").unwrap();
+        let client =
+            HomarrClient::new("http://localhost:7575/homarr", RequestPolicy::default()).unwrap();
         assert_eq!(client.base_url, "http://localhost:7575/homarr");
     }
 
-    // find_next_position tests
-    #[test]
-    fn test_find_next_position_empty_board() {
-        let client = create_test_client();
-        let items: Vec<serde_json::Value> = vec![];
-        let (x, y) = client.find_next_position(&items, 10);
-        assert_eq!((x, y), (0, 0));
+    // Retry tests
+
+    /// Serve HTTP responses with the given status codes in turn (the last one
+    /// repeats) and count the requests
+    async fn serve_statuses(statuses: Vec<u16>) -> (String, Arc<std::sync::atomic::AtomicUsize>) {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let count = Arc::new(AtomicUsize::new(0));
+        let served = count.clone();
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let n = served.fetch_add(1, Ordering::SeqCst);
+                let status = statuses[n.min(statuses.len() - 1)];
+                let mut buf = [0u8; 4096];
+                let _ = stream.read(&mut buf).await;
+                let body = r#"{"result":{"data":{"json":[]}}}"#;
+                let response = format!(
+                    "HTTP/1.1 {} X\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+        (base_url, count)
     }
 
-    #[test]
-    fn test_find_next_position_single_item() {
-        let client = create_test_client();
-        let items = vec![json!({
-            "layouts": [{
-                "xOffset": 0,
-                "yOffset": 0,
-                "width": 1,
-                "height": 1
-            }]
-        })];
-        let (x, y) = client.find_next_position(&items, 10);
-        // Should place next to the existing item
-        assert_eq!((x, y), (1, 0));
+    fn fast_policy() -> RequestPolicy {
+        RequestPolicy {
+            max_retries: 3,
+            backoff: retry::Backoff {
+                initial: std::time::Duration::from_millis(1),
+                max: std::time::Duration::from_millis(5),
+            },
+            ..Default::default()
+        }
     }
 
-    #[test]
-    fn test_find_next_position_full_row() {
-        let client = create_test_client();
-        // Fill all 10 columns in row 0
-        let items: Vec<serde_json::Value> = (0..10)
-            .map(|i| {
-                json!({
-                    "layouts": [{
-                        "xOffset": i,
-                        "yOffset": 0,
-                        "width": 1,
-                        "height": 1
-                    }]
-                })
-            })
-            .collect();
-        let (x, y) = client.find_next_position(&items, 10);
-        // Should start a new row
-        assert_eq!((x, y), (0, 1));
-    }
+    #[tokio::test]
+    async fn test_queries_retried_on_server_errors() {
+        let (base_url, count) = serve_statuses(vec![503, 502, 200]).await;
+        let client = HomarrClient::new(&base_url, fast_policy()).unwrap();
 
-    #[test]
-    fn test_find_next_position_with_gap() {
-        let client = create_test_client();
-        // Items at positions 0 and 2, leaving gap at 1
-        let items = vec![
-            json!({
-                "layouts": [{
-                    "xOffset": 0,
-                    "yOffset": 0,
-                    "width": 1,
-                    "height": 1
-                }]
-            }),
-            json!({
-                "layouts": [{
-                    "xOffset": 2,
-                    "yOffset": 0,
-                    "width": 1,
-                    "height": 1
-                }]
-            }),
-        ];
-        let (x, y) = client.find_next_position(&items, 10);
-        // Should fill the gap at position 1
-        assert_eq!((x, y), (1, 0));
-    }
+        let apps = client.get_all_apps().await.unwrap();
 
-    #[test]
-    fn test_find_next_position_wide_item() {
-        let client = create_test_client();
-        // Wide item taking columns 0-2
-        let items = vec![json!({
-            "layouts": [{
-                "xOffset": 0,
-                "yOffset": 0,
-                "width": 3,
-                "height": 1
-            }]
-        })];
-        let (x, y) = client.find_next_position(&items, 10);
-        // Should place at column 3
-        assert_eq!((x, y), (3, 0));
+        assert!(apps.is_empty());
+        assert_eq!(count.load(std::sync::atomic::Ordering::SeqCst), 3);
     }
 
-    #[test]
-    fn test_find_next_position_tall_item() {
-        let client = create_test_client();
-        // Tall item at position 0
-        let items = vec![json!({
-            "layouts": [{
-                "xOffset": 0,
-                "yOffset": 0,
-                "width": 1,
-                "height": 3
-            }]
-        })];
-        let (x, y) = client.find_next_position(&items, 10);
-        // Should place in the same row but different column
-        assert_eq!((x, y), (1, 2));
-    }
+    #[tokio::test]
+    async fn test_retries_are_bounded() {
+        let (base_url, count) = serve_statuses(vec![500]).await;
+        let client = HomarrClient::new(&base_url, fast_policy()).unwrap();
 
-    #[test]
-    fn test_find_next_position_multiple_rows() {
-        let client = create_test_client();
-        // Items in multiple rows
-        let items = vec![
-            json!({
-                "layouts": [{
-                    "xOffset": 0,
-                    "yOffset": 0,
-                    "width": 10,
-                    "height": 1
-                }]
-            }),
-            json!({
-                "layouts": [{
-                    "xOffset": 0,
-                    "yOffset": 1,
-                    "width": 5,
-                    "height": 1
-                }]
-            }),
-        ];
-        let (x, y) = client.find_next_position(&items, 10);
-        // Should place after the item in row 1
-        assert_eq!((x, y), (5, 1));
+        assert!(client.get_all_apps().await.is_err());
+        assert_eq!(count.load(std::sync::atomic::Ordering::SeqCst), 4);
     }
 
-    #[test]
-    fn test_find_next_position_small_column_count() {
-        let client = create_test_client();
-        // Fill a 3-column board
-        let items: Vec<serde_json::Value> = (0..3)
-            .map(|i| {
-                json!({
-                    "layouts": [{
-                        "xOffset": i,
-                        "yOffset": 0,
-                        "width": 1,
-                        "height": 1
-                    }]
-                })
-            })
-            .collect();
-        let (x, y) = client.find_next_position(&items, 3);
-        // Should start a new row
-        assert_eq!((x, y), (0, 1));
-    }
+    #[tokio::test]
+    async fn test_mutations_not_retried() {
+        let (base_url, count) = serve_statuses(vec![503, 200]).await;
+        let client = HomarrClient::new(&base_url, fast_policy()).unwrap();
 
-    #[test]
-    fn test_find_next_position_items_without_layouts() {
-        let client = create_test_client();
-        // Items missing layouts field
-        let items = vec![json!({"id": "item1"}), json!({"layouts": []})];
-        let (x, y) = client.find_next_position(&items, 10);
-        // Should handle gracefully and start at origin
-        assert_eq!((x, y), (0, 0));
+        // Creating twice would leave an extra key behind
+        assert!(client.create_api_key().await.is_err());
+        assert_eq!(count.load(std::sync::atomic::Ordering::SeqCst), 1);
     }
 
     // transform_icon_url tests
@@ -1332,6 +1431,185 @@ mod tests {
         assert!(!board_has_app(&items, "any-app-id"));
     }
 
+    #[test]
+    fn test_board_app_items() {
+        let items = vec![
+            json!({"id": "item-1", "kind": "app", "options": {"appId": "app-1"}}),
+            json!({"id": "item-2", "kind": "clock", "options": {}}),
+            json!({"id": "item-3", "kind": "app", "options": {"appId": "app-3"}}),
+        ];
+
+        assert_eq!(
+            board_app_items(&items),
+            vec![("item-1", "app-1"), ("item-3", "app-3")]
+        );
+    }
+
+    #[test]
+    fn test_api_error_types_rejected_key() {
+        assert!(matches!(
+            api_error(reqwest::StatusCode::UNAUTHORIZED, "denied".to_string()),
+            AdapterError::Unauthorized(_)
+        ));
+        // Forbidden is about a missing permission, not the key
+        assert!(matches!(
+            api_error(reqwest::StatusCode::FORBIDDEN, "denied".to_string()),
+            AdapterError::HomarrApi(_)
+        ));
+    }
+
+    #[test]
+    fn test_fnv1a_hash_known_values() {
+        assert_eq!(fnv1a_hash(""), 0xcbf29ce484222325);
+        assert_eq!(fnv1a_hash("a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(fnv1a_hash("foobar"), 0x85944171f73967e8);
+    }
+
+    #[test]
+    fn test_board_item_id_is_stable() {
+        let app: AppDefinition = toml::from_str(
+            r#"
+name = "Web"
+url = "http://localhost:8080"
+"#,
+        )
+        .unwrap();
+        // Changing this ID duplicates the tiles on every existing board
+        assert_eq!(board_item_id(&app), "registry-2e0273c412e7955a");
+        assert!(legacy_board_item_id(&app).is_some());
+
+        let container: AppDefinition = toml::from_str(
+            r#"
+name = "Signal K"
+url = "http://localhost:3000"
+
+[type]
+container_name = "signalk-server"
+"#,
+        )
+        .unwrap();
+        assert_eq!(board_item_id(&container), "registry-signalk-server");
+        assert_eq!(legacy_board_item_id(&container), None);
+    }
+
+    // Tests for category sections
+
+    fn section(id: &str, kind: &str, y: i32, name: Option<&str>) -> Section {
+        Section {
+            id: id.to_string(),
+            kind: kind.to_string(),
+            y_offset: y,
+            x_offset: 0,
+            name: name.map(str::to_string),
+            extra: serde_json::Map::new(),
+        }
+    }
+
+    fn categories(list: &[(&str, u8)]) -> Vec<(String, u8)> {
+        list.iter().map(|(n, p)| (n.to_string(), *p)).collect()
+    }
+
+    fn category_order(sections: &[Section]) -> Vec<String> {
+        let mut cats: Vec<&Section> = sections
+            .iter()
+            .filter(|s| s.kind == SECTION_KIND_CATEGORY)
+            .collect();
+        cats.sort_by_key(|s| s.y_offset);
+        cats.iter().map(|s| s.name.clone().unwrap()).collect()
+    }
+
+    #[test]
+    fn test_section_deserialize_preserves_extra_fields() {
+        let json = r#"{"id": "s1", "kind": "category", "yOffset": 1, "xOffset": 0,
+                       "name": "Marine", "collapsed": true}"#;
+        let section: Section = serde_json::from_str(json).unwrap();
+        assert_eq!(section.name.as_deref(), Some("Marine"));
+
+        let value = serde_json::to_value(&section).unwrap();
+        assert_eq!(value["collapsed"], json!(true));
+        assert_eq!(value["name"], json!("Marine"));
+    }
+
+    #[test]
+    fn test_arrange_category_sections_creates_missing() {
+        let mut sections = vec![section("s0", "empty", 0, None)];
+        let changed = arrange_category_sections(
+            &mut sections,
+            &categories(&[("System", 10), ("Marine", 25)]),
+        );
+
+        assert!(changed);
+        // Each category is followed by an empty section
+        assert_eq!(sections.len(), 5);
+        assert_eq!(category_order(&sections), vec!["System", "Marine"]);
+        assert!(sections
+            .iter()
+            .any(|s| s.id == "registry-category-system" && s.kind == "category"));
+    }
+
+    #[test]
+    fn test_arrange_category_sections_reuses_existing_by_name() {
+        let mut sections = vec![
+            section("s0", "empty", 0, None),
+            section("user-marine", "category", 1, Some("marine")),
+            section("s2", "empty", 2, None),
+        ];
+        let changed = arrange_category_sections(&mut sections, &categories(&[("Marine", 25)]));
+
+        assert!(!changed);
+        assert_eq!(sections.len(), 3);
+    }
+
+    #[test]
+    fn test_arrange_category_sections_orders_by_priority() {
+        let mut sections = vec![
+            section("s0", "empty", 0, None),
+            section("c-marine", "category", 1, Some("Marine")),
+            section("s2", "empty", 2, None),
+            section("c-user", "category", 3, Some("My Stuff")),
+            section("s4", "empty", 4, None),
+            section("c-system", "category", 5, Some("System")),
+            section("s6", "empty", 6, None),
+        ];
+        let changed =
+            arrange_category_sections(&mut sections, &categories(&[("System", 5), ("Marine", 25)]));
+
+        assert!(changed);
+        assert_eq!(
+            category_order(&sections),
+            vec!["System", "My Stuff", "Marine"]
+        );
+        // User category keeps its slot
+        assert_eq!(
+            sections.iter().find(|s| s.id == "c-user").unwrap().y_offset,
+            3
+        );
+    }
+
+    #[test]
+    fn test_section_for_app() {
+        let sections = vec![
+            section("c-marine", "category", 1, Some("Marine")),
+            section("s0", "empty", 0, None),
+            section("s2", "empty", 2, None),
+        ];
+
+        assert_eq!(
+            section_for_app(&sections, Some("Marine")).unwrap().id,
+            "c-marine"
+        );
+        assert_eq!(
+            section_for_app(&sections, Some("MARINE")).unwrap().id,
+            "c-marine"
+        );
+        assert_eq!(section_for_app(&sections, None).unwrap().id, "s0");
+        assert_eq!(
+            section_for_app(&sections, Some("Unknown")).unwrap().id,
+            "s0"
+        );
+        assert!(section_for_app(&[], None).is_none());
+    }
+
     // Tests for derive_ping_url (auto-derive host.docker.internal URL for health checks)
 
     #[test]
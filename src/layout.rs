@@ -0,0 +1,455 @@
+//! Board grid placement
+//!
+//! Homarr lays out items on a grid with a fixed number of columns per board
+//! layout and unlimited rows. Each section (empty or category) has its own grid.
+//! `OccupancyGrid` tracks which cells are taken so that new tiles can be packed
+//! into the first free spot without overlapping existing ones.
+//!
+//! `reflow()` recomputes the positions of adapter-owned tiles so a board follows
+//! registry priority, leaving every other item where the user put it.
+
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+/// Column count used when a board has no layouts
+pub const DEFAULT_COLUMN_COUNT: i32 = 12;
+
+/// Cells occupied in one section of a board layout
+#[derive(Debug, Clone)]
+pub struct OccupancyGrid {
+    columns: i32,
+    cells: HashSet<(i32, i32)>,
+}
+
+impl OccupancyGrid {
+    /// Create an empty grid with the given number of columns
+    pub fn new(columns: i32) -> Self {
+        Self {
+            columns: columns.max(1),
+            cells: HashSet::new(),
+        }
+    }
+
+    /// Build a grid from the board items placed in a section
+    ///
+    /// Only item layouts in `section_id` count. If `layout_id` is non-empty,
+    /// layouts for other board layouts (breakpoints) are ignored.
+    pub fn from_items(
+        items: &[serde_json::Value],
+        section_id: &str,
+        layout_id: &str,
+        columns: i32,
+    ) -> Self {
+        let mut grid = Self::new(columns);
+
+        for item in items {
+            let Some(layouts) = item.get("layouts").and_then(|l| l.as_array()) else {
+                continue;
+            };
+            for layout in layouts {
+                let field = |name: &str| layout.get(name).and_then(|v| v.as_str());
+                if field("sectionId") != Some(section_id) {
+                    continue;
+                }
+                if !layout_id.is_empty() && field("layoutId").is_some_and(|id| id != layout_id) {
+                    continue;
+                }
+
+                let number = |name: &str, default: i64| {
+                    layout.get(name).and_then(|v| v.as_i64()).unwrap_or(default) as i32
+                };
+                grid.occupy(
+                    number("xOffset", 0),
+                    number("yOffset", 0),
+                    number("width", 1),
+                    number("height", 1),
+                );
+            }
+        }
+
+        grid
+    }
+
+    /// Mark a rectangle as occupied
+    pub fn occupy(&mut self, x: i32, y: i32, width: i32, height: i32) {
+        for row in y..y + height.max(1) {
+            for col in x..x + width.max(1) {
+                self.cells.insert((col, row));
+            }
+        }
+    }
+
+    /// Check if a rectangle fits in the grid without overlapping anything
+    pub fn is_free(&self, x: i32, y: i32, width: i32, height: i32) -> bool {
+        if x < 0 || y < 0 || x + width > self.columns {
+            return false;
+        }
+        (y..y + height).all(|row| (x..x + width).all(|col| !self.cells.contains(&(col, row))))
+    }
+
+    /// Find the first free spot for a tile, scanning rows top to bottom and
+    /// columns left to right
+    ///
+    /// Tiles wider than the grid are clamped to the grid width.
+    pub fn find_free(&self, width: i32, height: i32) -> (i32, i32) {
+        let width = width.clamp(1, self.columns);
+        let height = height.max(1);
+
+        // A free spot always exists at or before the row below the lowest item
+        let last_row = self.cells.iter().map(|(_, row)| row + 1).max().unwrap_or(0);
+        for y in 0..=last_row {
+            for x in 0..=(self.columns - width) {
+                if self.is_free(x, y, width, height) {
+                    return (x, y);
+                }
+            }
+        }
+
+        (0, last_row)
+    }
+
+    /// Find the first free spot for a tile and mark it as occupied
+    pub fn place(&mut self, width: i32, height: i32) -> (i32, i32) {
+        let (x, y) = self.find_free(width, height);
+        self.occupy(x, y, width.clamp(1, self.columns), height);
+        (x, y)
+    }
+}
+
+/// How an adapter-owned tile is positioned during a reflow
+#[derive(Debug, Clone, Copy)]
+pub struct ManagedTile {
+    /// Position in priority order (lower comes first)
+    pub rank: usize,
+
+    /// Explicit position from the registry, kept as is
+    pub pinned: Option<(i32, i32)>,
+}
+
+/// A tile moved by a reflow
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TileMove {
+    pub item_id: String,
+    pub section_id: String,
+    pub from_x: i32,
+    pub from_y: i32,
+    pub x_offset: i32,
+    pub y_offset: i32,
+}
+
+/// Recompute positions of adapter-owned tiles in priority order
+///
+/// `managed` maps board item IDs to their rank. Every other item stays fixed
+/// and is packed around. Within each section, pinned tiles are reserved first,
+/// then the remaining managed tiles are placed in the first free spot in rank
+/// order, keeping their current size. Only tiles whose position changes are
+/// returned.
+pub fn reflow(
+    items: &[serde_json::Value],
+    layout_id: &str,
+    column_count: i32,
+    managed: &HashMap<String, ManagedTile>,
+) -> Vec<TileMove> {
+    struct Tile {
+        item_id: String,
+        section_id: String,
+        tile: ManagedTile,
+        x: i32,
+        y: i32,
+        width: i32,
+        height: i32,
+    }
+
+    let mut grids: HashMap<String, OccupancyGrid> = HashMap::new();
+    let mut tiles = Vec::new();
+
+    for item in items {
+        let item_id = item.get("id").and_then(|v| v.as_str()).unwrap_or_default();
+        let Some(layouts) = item.get("layouts").and_then(|l| l.as_array()) else {
+            continue;
+        };
+        for layout in layouts {
+            let field = |name: &str| layout.get(name).and_then(|v| v.as_str());
+            if !layout_id.is_empty() && field("layoutId").is_some_and(|id| id != layout_id) {
+                continue;
+            }
+            let section_id = field("sectionId").unwrap_or_default().to_string();
+            let number = |name: &str, default: i64| {
+                layout.get(name).and_then(|v| v.as_i64()).unwrap_or(default) as i32
+            };
+            let (x, y) = (number("xOffset", 0), number("yOffset", 0));
+            let (width, height) = (number("width", 1), number("height", 1));
+
+            let grid = grids
+                .entry(section_id.clone())
+                .or_insert_with(|| OccupancyGrid::new(column_count));
+            match managed.get(item_id) {
+                Some(tile) => tiles.push(Tile {
+                    item_id: item_id.to_string(),
+                    section_id,
+                    tile: *tile,
+                    x,
+                    y,
+                    width,
+                    height,
+                }),
+                None => grid.occupy(x, y, width, height),
+            }
+        }
+    }
+
+    tiles.sort_by_key(|t| (t.tile.pinned.is_none(), t.tile.rank));
+
+    let mut moves = Vec::new();
+    for tile in tiles {
+        let grid = grids
+            .get_mut(&tile.section_id)
+            .expect("grid created for every section with items");
+        let (x, y) = match tile.tile.pinned {
+            Some((x, y)) => {
+                grid.occupy(x, y, tile.width, tile.height);
+                (x, y)
+            }
+            None => grid.place(tile.width, tile.height),
+        };
+
+        if (x, y) != (tile.x, tile.y) {
+            moves.push(TileMove {
+                item_id: tile.item_id,
+                section_id: tile.section_id,
+                from_x: tile.x,
+                from_y: tile.y,
+                x_offset: x,
+                y_offset: y,
+            });
+        }
+    }
+
+    moves
+}
+
+/// Apply reflow moves to board items
+///
+/// Only the layout for `layout_id` is changed; other breakpoints are kept.
+pub fn apply_moves(items: &mut [serde_json::Value], layout_id: &str, moves: &[TileMove]) {
+    for tile_move in moves {
+        let Some(item) = items
+            .iter_mut()
+            .find(|i| i.get("id").and_then(|v| v.as_str()) == Some(tile_move.item_id.as_str()))
+        else {
+            continue;
+        };
+        let Some(layouts) = item.get_mut("layouts").and_then(|l| l.as_array_mut()) else {
+            continue;
+        };
+        for layout in layouts {
+            let field = |name: &str| layout.get(name).and_then(|v| v.as_str());
+            if field("sectionId") != Some(tile_move.section_id.as_str())
+                || (!layout_id.is_empty() && field("layoutId").is_some_and(|id| id != layout_id))
+            {
+                continue;
+            }
+            layout["xOffset"] = tile_move.x_offset.into();
+            layout["yOffset"] = tile_move.y_offset.into();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn tile(x: i32, y: i32, width: i32, height: i32) -> serde_json::Value {
+        json!({
+            "layouts": [{
+                "layoutId": "layout-1",
+                "sectionId": "main",
+                "xOffset": x,
+                "yOffset": y,
+                "width": width,
+                "height": height
+            }]
+        })
+    }
+
+    fn next_position(items: &[serde_json::Value], columns: i32) -> (i32, i32) {
+        OccupancyGrid::from_items(items, "main", "layout-1", columns).find_free(1, 1)
+    }
+
+    #[test]
+    fn test_empty_board() {
+        assert_eq!(next_position(&[], 10), (0, 0));
+    }
+
+    #[test]
+    fn test_single_item() {
+        // Should place next to the existing item
+        assert_eq!(next_position(&[tile(0, 0, 1, 1)], 10), (1, 0));
+    }
+
+    #[test]
+    fn test_full_row() {
+        // Fill all 10 columns in row 0, should start a new row
+        let items: Vec<_> = (0..10).map(|i| tile(i, 0, 1, 1)).collect();
+        assert_eq!(next_position(&items, 10), (0, 1));
+    }
+
+    #[test]
+    fn test_fills_gap() {
+        // Items at positions 0 and 2, leaving gap at 1
+        let items = vec![tile(0, 0, 1, 1), tile(2, 0, 1, 1)];
+        assert_eq!(next_position(&items, 10), (1, 0));
+    }
+
+    #[test]
+    fn test_wide_item() {
+        // Wide item taking columns 0-2
+        assert_eq!(next_position(&[tile(0, 0, 3, 1)], 10), (3, 0));
+    }
+
+    #[test]
+    fn test_tall_item() {
+        // Tall item at column 0 leaves the rest of row 0 free
+        assert_eq!(next_position(&[tile(0, 0, 1, 3)], 10), (1, 0));
+    }
+
+    #[test]
+    fn test_multiple_rows() {
+        let items = vec![tile(0, 0, 10, 1), tile(0, 1, 5, 1)];
+        // Should place after the item in row 1
+        assert_eq!(next_position(&items, 10), (5, 1));
+    }
+
+    #[test]
+    fn test_small_column_count() {
+        let items: Vec<_> = (0..3).map(|i| tile(i, 0, 1, 1)).collect();
+        assert_eq!(next_position(&items, 3), (0, 1));
+    }
+
+    #[test]
+    fn test_items_without_layouts() {
+        let items = vec![json!({"id": "item1"}), json!({"layouts": []})];
+        assert_eq!(next_position(&items, 10), (0, 0));
+    }
+
+    #[test]
+    fn test_fills_hole_left_by_removed_item() {
+        // Row 0 full except column 1, row 1 partially used
+        let mut items: Vec<_> = (0..4)
+            .filter(|i| *i != 1)
+            .map(|i| tile(i, 0, 1, 1))
+            .collect();
+        items.push(tile(0, 1, 2, 1));
+        assert_eq!(next_position(&items, 4), (1, 0));
+    }
+
+    #[test]
+    fn test_tall_item_above_bottom_row_not_overlapped() {
+        // Tall item in column 3 spans rows 0-2; bottom row only has column 0
+        let items = vec![tile(0, 0, 3, 2), tile(3, 0, 1, 3), tile(0, 2, 1, 1)];
+        let grid = OccupancyGrid::from_items(&items, "main", "layout-1", 4);
+        // Column 3 in row 2 is taken by the tall item
+        assert_eq!(grid.find_free(2, 1), (1, 2));
+        assert_eq!(grid.find_free(3, 1), (0, 3));
+    }
+
+    #[test]
+    fn test_wide_tile_needs_contiguous_columns() {
+        let items = vec![tile(1, 0, 1, 1), tile(3, 0, 1, 1)];
+        let grid = OccupancyGrid::from_items(&items, "main", "layout-1", 4);
+        assert_eq!(grid.find_free(1, 1), (0, 0));
+        assert_eq!(grid.find_free(2, 1), (0, 1));
+    }
+
+    #[test]
+    fn test_tile_wider_than_grid_is_clamped() {
+        let grid = OccupancyGrid::new(3);
+        assert_eq!(grid.find_free(5, 1), (0, 0));
+    }
+
+    #[test]
+    fn test_place_reserves_cells() {
+        let mut grid = OccupancyGrid::new(3);
+        assert_eq!(grid.place(2, 2), (0, 0));
+        assert_eq!(grid.place(1, 1), (2, 0));
+        assert_eq!(grid.place(1, 1), (2, 1));
+        assert_eq!(grid.place(2, 1), (0, 2));
+    }
+
+    #[test]
+    fn test_from_items_filters_section_and_layout() {
+        let items = vec![
+            json!({"layouts": [{"layoutId": "layout-1", "sectionId": "other",
+                                "xOffset": 0, "yOffset": 0, "width": 1, "height": 1}]}),
+            json!({"layouts": [{"layoutId": "layout-2", "sectionId": "main",
+                                "xOffset": 0, "yOffset": 0, "width": 1, "height": 1}]}),
+            tile(1, 0, 1, 1),
+        ];
+        let grid = OccupancyGrid::from_items(&items, "main", "layout-1", 4);
+        assert!(grid.is_free(0, 0, 1, 1));
+        assert!(!grid.is_free(1, 0, 1, 1));
+    }
+
+    fn owned(id: &str, x: i32, y: i32, width: i32) -> serde_json::Value {
+        let mut item = tile(x, y, width, 1);
+        item["id"] = json!(id);
+        item
+    }
+
+    fn managed(ids: &[&str]) -> HashMap<String, ManagedTile> {
+        ids.iter()
+            .enumerate()
+            .map(|(rank, id)| (id.to_string(), ManagedTile { rank, pinned: None }))
+            .collect()
+    }
+
+    #[test]
+    fn test_reflow_orders_by_rank_around_user_items() {
+        // "low" has the lowest rank but was added last, at the bottom
+        let items = vec![
+            owned("high", 0, 0, 1),
+            owned("user", 1, 0, 1),
+            owned("mid", 2, 0, 1),
+            owned("low", 0, 1, 2),
+        ];
+
+        let moves = reflow(&items, "layout-1", 3, &managed(&["low", "mid", "high"]));
+
+        let targets: Vec<_> = moves
+            .iter()
+            .map(|m| (m.item_id.as_str(), m.x_offset, m.y_offset))
+            .collect();
+        // The user item stays at (1, 0), so "low" (two columns wide) still
+        // only fits in row 1 and is not reported as moved
+        assert_eq!(targets, vec![("mid", 0, 0), ("high", 2, 0)]);
+    }
+
+    #[test]
+    fn test_reflow_keeps_pinned_and_reports_only_changes() {
+        let items = vec![owned("a", 0, 0, 1), owned("b", 1, 0, 1)];
+        let mut managed = managed(&["b", "a"]);
+        managed.get_mut("a").unwrap().pinned = Some((0, 0));
+
+        // "a" is pinned at (0, 0), "b" is already in the first free spot
+        assert!(reflow(&items, "layout-1", 4, &managed).is_empty());
+    }
+
+    #[test]
+    fn test_apply_moves() {
+        let mut items = vec![owned("a", 3, 2, 1)];
+        let moves = vec![TileMove {
+            item_id: "a".to_string(),
+            section_id: "main".to_string(),
+            from_x: 3,
+            from_y: 2,
+            x_offset: 0,
+            y_offset: 0,
+        }];
+
+        apply_moves(&mut items, "layout-1", &moves);
+
+        assert_eq!(items[0]["layouts"][0]["xOffset"], json!(0));
+        assert_eq!(items[0]["layouts"][0]["yOffset"], json!(0));
+    }
+}
@@ -3,25 +3,36 @@
 //! This service provides:
 //! - First-boot setup: Completes Homarr onboarding with HaLOS branding
 //! - App registry: Syncs apps from /etc/halos/webapps.d/ to Homarr dashboard
+//! - Container discovery: Adds containers with `homarr.*` labels to the registry
 //! - Watch mode: Daemon that monitors Docker events and syncs on changes
 
+mod apikey;
 mod authelia;
 mod branding;
 mod config;
+mod docker;
 mod error;
+mod health;
 mod homarr;
+mod layout;
+mod metrics;
 mod registry;
+mod retry;
 mod state;
+mod status;
+mod sync;
+mod systemd;
+mod watch;
 
 use std::collections::HashMap;
 use std::time::Duration;
 
-use bollard::container::ListContainersOptions;
+use bollard::models::EventMessage;
 use bollard::system::EventsOptions;
 use bollard::Docker;
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 use futures_util::StreamExt;
-use tokio::time::{interval, sleep};
+use tokio::time::{interval, sleep, sleep_until, Instant};
 use tracing::{debug, error, info, warn, Level};
 use tracing_subscriber::FmtSubscriber;
 
@@ -59,18 +70,61 @@ struct Cli {
 #[derive(Subcommand)]
 enum Commands {
     /// Run a sync cycle (load registry and sync to Homarr)
-    Sync,
+    Sync {
+        /// Print the sync plan instead of applying it (same as `plan`)
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Show what a sync would change without touching Homarr
+    Plan {
+        /// Output format
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+
+    /// Move adapter-managed tiles into priority order
+    ///
+    /// Tiles placed by users stay where they are.
+    Relayout {
+        /// Print the moves instead of applying them
+        #[arg(long)]
+        dry_run: bool,
+    },
 
     /// Run first-boot setup only
     Setup,
 
     /// Check adapter status
-    Status,
+    Status {
+        /// Output format
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+
+        /// Also query Homarr and Docker
+        #[arg(long)]
+        live: bool,
+
+        /// Compare the registry, the state and Homarr, and report differences
+        #[arg(long)]
+        drift: bool,
+    },
 
     /// Watch for Docker events and sync continuously (daemon mode)
     Watch,
 }
 
+/// Output format for machine-readable commands
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum OutputFormat {
+    /// Human-readable text
+    Text,
+    /// JSON
+    Json,
+    /// YAML
+    Yaml,
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     let cli = Cli::parse();
@@ -92,16 +146,29 @@ async fn main() -> Result<()> {
     }
 
     match cli.command {
-        Commands::Sync => {
+        Commands::Sync { dry_run: true } => {
+            run_plan(&config, OutputFormat::Text).await?;
+        }
+        Commands::Sync { dry_run: false } => {
             info!("Running sync cycle");
             run_sync(&config).await?;
         }
+        Commands::Plan { format } => {
+            run_plan(&config, format).await?;
+        }
+        Commands::Relayout { dry_run } => {
+            run_relayout(&config, dry_run).await?;
+        }
         Commands::Setup => {
             info!("Running first-boot setup");
             run_setup(&config).await?;
         }
-        Commands::Status => {
-            check_status(&config).await?;
+        Commands::Status {
+            format,
+            live,
+            drift,
+        } => {
+            check_status(&config, format, live, drift).await?;
         }
         Commands::Watch => {
             info!("Starting watch mode (daemon)");
@@ -124,133 +191,240 @@ async fn run_sync(config: &Config) -> Result<()> {
     }
 
     // Create client and set up authentication
-    let mut client = homarr::HomarrClient::new(&config.homarr_url)?;
+    let mut client = homarr::HomarrClient::new(
+        &config.homarr_url,
+        retry::RequestPolicy::from_config(config),
+    )?;
     ensure_authenticated(&mut client, config, &mut state).await?;
 
-    // Discover writable boards
-    let writable_boards = client.get_writable_boards().await.unwrap_or_else(|e| {
-        warn!("Failed to fetch writable boards: {}", e);
-        vec![]
-    });
-
-    if writable_boards.is_empty() {
-        warn!("No writable boards found, skipping sync");
+    let Some(inputs) = sync::gather_inputs(&client, config).await else {
         return Ok(());
-    }
+    };
+
+    // Decide what to change, then apply it
+    let plan = sync::build_plan(&mut state, &inputs);
+    let report = sync::execute_plan(
+        &client,
+        &mut state,
+        &plan,
+        std::path::Path::new(&config.state_file),
+    )
+    .await;
+
+    state.update_sync_time();
+    state.save(&config.state_file)?;
 
     info!(
-        "Found {} writable board(s): {}",
-        writable_boards.len(),
-        writable_boards
-            .iter()
-            .map(|b| b.name.as_str())
-            .collect::<Vec<_>>()
-            .join(", ")
+        "Sync complete: {} created, {} updated, {} tile(s) added, {} moved, {} pruned, {} failed",
+        report.created, report.updated, report.added, report.moved, report.pruned, report.failed
     );
+    Ok(())
+}
 
-    // Pre-fetch existing apps for efficient deduplication
-    let existing_apps = client.get_all_apps().await.unwrap_or_else(|e| {
-        warn!("Failed to fetch existing apps: {}", e);
-        vec![]
-    });
-
-    // Load registry apps
-    info!("Loading apps from registry: {}", config.registry_dir);
-    let registry_apps = registry::load_all_apps(&config.registry_dir).unwrap_or_else(|e| {
-        warn!("Failed to load registry apps: {}", e);
-        vec![]
-    });
-
-    // Filter to visible apps only
-    let visible_apps: Vec<_> = registry_apps
-        .iter()
-        .filter(|e| e.app.is_visible())
-        .collect();
-    let hidden_count = registry_apps.len() - visible_apps.len();
-    if hidden_count > 0 {
-        debug!(
-            "Filtered out {} hidden app(s) from {} total",
-            hidden_count,
-            registry_apps.len()
-        );
+/// Compute the sync plan and print it without changing Homarr or the state file
+async fn run_plan(config: &Config, format: OutputFormat) -> Result<()> {
+    let mut state = state::State::load_readonly(&config.state_file)?;
+
+    if !state.first_boot_completed {
+        return Err(AdapterError::State(
+            "First-boot setup has not run yet; run 'setup' or 'sync' first".to_string(),
+        ));
     }
 
-    // Sync each visible app to each writable board
-    let mut synced_count = 0;
-    for entry in &visible_apps {
-        // Track app in discovered_apps (once per app, not per board)
-        let container_id = entry.app.container_name().unwrap_or("").to_string();
-        state.discovered_apps.insert(
-            entry.app.url.clone(),
-            state::DiscoveredApp {
-                name: entry.app.name.clone(),
-                container_id,
-                added_at: chrono::Utc::now(),
-            },
-        );
+    // Only use an existing key: rotating the bootstrap key would change Homarr
+    let Some(api_key) = apikey::stored_key(&state)? else {
+        return Err(AdapterError::State(
+            "No API key stored; run 'sync' once to rotate the bootstrap key".to_string(),
+        ));
+    };
+    let mut client = homarr::HomarrClient::new(
+        &config.homarr_url,
+        retry::RequestPolicy::from_config(config),
+    )?;
+    client.set_api_key(api_key);
 
-        // Sync to each writable board
-        for board in &writable_boards {
-            // Check if app was removed from this specific board
-            if state.is_removed_from_board(&board.id, &entry.app.url) {
-                debug!(
-                    "App '{}' was removed from board '{}', skipping",
-                    entry.app.name, board.name
-                );
-                continue;
-            }
+    let Some(inputs) = sync::gather_inputs(&client, config).await else {
+        return Ok(());
+    };
 
-            match client
-                .add_registry_app(&entry.app, &board.name, Some(&existing_apps))
-                .await
-            {
-                Ok(_) => {
-                    synced_count += 1;
-                }
-                Err(e) => {
-                    warn!(
-                        "Failed to add app '{}' to board '{}': {}",
-                        entry.app.name, board.name, e
-                    );
-                }
-            }
-        }
+    // Removal tracking in this copy of the state is updated, but never saved
+    let plan = sync::build_plan(&mut state, &inputs);
+
+    match format {
+        OutputFormat::Text => print!("{}", sync::render_text(&plan)),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&plan)?),
+        OutputFormat::Yaml => print!("{}", to_yaml(&plan)?),
     }
 
-    state.update_sync_time();
-    state.save(&config.state_file)?;
+    Ok(())
+}
+
+/// Reflow adapter-managed tiles on every writable board into priority order
+async fn run_relayout(config: &Config, dry_run: bool) -> Result<()> {
+    let mut state = state::State::load(&config.state_file)?;
 
+    if !state.first_boot_completed {
+        return Err(AdapterError::State(
+            "First-boot setup has not run yet; run 'setup' or 'sync' first".to_string(),
+        ));
+    }
+
+    let mut client = homarr::HomarrClient::new(
+        &config.homarr_url,
+        retry::RequestPolicy::from_config(config),
+    )?;
+    ensure_authenticated(&mut client, config, &mut state).await?;
+
+    let Some(inputs) = sync::gather_inputs(&client, config).await else {
+        return Ok(());
+    };
+
+    let plan = sync::build_relayout_plan(&mut state, &inputs);
+    if dry_run {
+        print!("{}", sync::render_text(&plan));
+        return Ok(());
+    }
+
+    let report = sync::execute_plan(
+        &client,
+        &mut state,
+        &plan,
+        std::path::Path::new(&config.state_file),
+    )
+    .await;
+    state.save(&config.state_file)?;
     info!(
-        "Sync complete: {} visible app(s), {} app-board combinations synced",
-        visible_apps.len(),
-        synced_count
+        "Relayout complete: {} tile(s) moved, {} failed",
+        report.moved, report.failed
     );
     Ok(())
 }
 
 /// Ensure the Homarr client is authenticated with a valid API key.
 ///
-/// If a permanent API key is stored in state, use it.
-/// Otherwise, rotate from the bootstrap API key to a new permanent key.
+/// If a permanent API key is stored in state and Homarr accepts it, use it.
+/// Otherwise, rotate from the bootstrap API key to a new permanent key. When
+/// neither key works, the failure is recorded in state for `status`.
 async fn ensure_authenticated(
     client: &mut homarr::HomarrClient,
     config: &Config,
     state: &mut state::State,
 ) -> Result<()> {
-    use std::fs;
+    // Keys stored by older versions or elsewhere move to the configured store
+    apikey::migrate_storage(config, state)?;
+    let stored_key = apikey::stored_key(state)?;
 
-    // Check if we already have a permanent API key
-    if let Some(ref api_key) = state.api_key {
-        info!("Using stored API key for authentication");
+    // Check if we already have a permanent API key that still works
+    if let Some(ref api_key) = stored_key {
         client.set_api_key(api_key.clone());
-        return Ok(());
+        match client.check_api_key().await {
+            Ok(true) => {
+                info!("Using stored API key for authentication");
+                clear_auth_failure(config, state)?;
+                if state.bootstrap_key_revoked_at.is_none() {
+                    check_bootstrap_revoked(client, config, state).await;
+                }
+                return Ok(());
+            }
+            Ok(false) => {
+                warn!("Homarr rejected the stored API key, rotating from bootstrap key");
+            }
+            Err(e) => {
+                // Homarr is unreachable or failing; the key may well be fine
+                warn!(
+                    "Could not validate the stored API key, using it anyway: {}",
+                    e
+                );
+                return Ok(());
+            }
+        }
+    } else {
+        info!("No permanent API key found, rotating from bootstrap key");
+    }
+
+    // A new key would be lost if it can't be stored, and the bootstrap key with it
+    let store = apikey::KeyStore::from_config(config);
+    if !store.is_writable() {
+        if stored_key.is_some() {
+            record_auth_failure(config, state, state::AuthFailure::KeyRejected);
+        }
+        return Err(AdapterError::Unauthorized(format!(
+            "no valid API key in {}, which the adapter can't replace; provision a new key",
+            store
+        )));
     }
 
-    // No permanent key - need to rotate from bootstrap key
-    info!("No permanent API key found, rotating from bootstrap key");
+    let bootstrap_key = if apikey::bootstrap_key_ignored(config, state) {
+        Err(AdapterError::Config(
+            "Bootstrap API key was already revoked and its file is no longer used".to_string(),
+        ))
+    } else {
+        read_bootstrap_key(config)
+    };
+    let bootstrap_key = match bootstrap_key {
+        Ok(key) => key,
+        Err(e) if stored_key.is_some() => {
+            record_auth_failure(config, state, state::AuthFailure::KeyRejected);
+            return Err(AdapterError::Unauthorized(format!(
+                "stored API key is no longer valid and no bootstrap key is available ({})",
+                e
+            )));
+        }
+        Err(e) => return Err(e),
+    };
+
+    // Rotate to permanent key
+    let permanent_key = match client.rotate_api_key(&bootstrap_key).await {
+        Ok(key) => key,
+        Err(AdapterError::Unauthorized(e)) => {
+            record_auth_failure(config, state, state::AuthFailure::BootstrapRejected);
+            return Err(AdapterError::Unauthorized(format!(
+                "bootstrap API key from {} is not valid either ({})",
+                config.bootstrap_api_key_file, e
+            )));
+        }
+        Err(e) => return Err(e),
+    };
+
+    // Store the permanent key; removal history and tracked apps are kept
+    state.auth_failure = None;
+    apikey::store_key(config, state, &permanent_key)?;
+
+    info!(
+        "API key rotation complete, permanent key saved to {}",
+        store
+    );
+
+    check_bootstrap_revoked(client, config, state).await;
+    Ok(())
+}
 
-    // Read bootstrap key from file
-    let bootstrap_key = fs::read_to_string(&config.bootstrap_api_key_file)
+/// Make sure Homarr rejects the bootstrap key, if that hasn't been verified yet
+///
+/// Failures are logged; the check is repeated on the next run.
+async fn check_bootstrap_revoked(
+    client: &homarr::HomarrClient,
+    config: &Config,
+    state: &mut state::State,
+) {
+    let bootstrap_key = match read_bootstrap_key(config) {
+        Ok(key) => key,
+        Err(e) => {
+            debug!("Not verifying bootstrap key revocation: {}", e);
+            return;
+        }
+    };
+    if let Err(e) = apikey::verify_bootstrap_revoked(client, config, state, &bootstrap_key).await {
+        warn!(
+            "Could not verify that Homarr rejects the bootstrap API key: {}",
+            e
+        );
+    }
+}
+
+/// Read the bootstrap API key from its file
+fn read_bootstrap_key(config: &Config) -> Result<String> {
+    let bootstrap_key = std::fs::read_to_string(&config.bootstrap_api_key_file)
         .map_err(|e| {
             AdapterError::Config(format!(
                 "Failed to read bootstrap API key from {}: {}",
@@ -266,14 +440,24 @@ async fn ensure_authenticated(
         ));
     }
 
-    // Rotate to permanent key
-    let permanent_key = client.rotate_api_key(&bootstrap_key).await?;
+    Ok(bootstrap_key)
+}
 
-    // Save the permanent key to state
-    state.api_key = Some(permanent_key.clone());
-    state.save(&config.state_file)?;
+/// Record in state why authentication failed, so `status` can show it
+fn record_auth_failure(config: &Config, state: &mut state::State, failure: state::AuthFailure) {
+    error!("Authentication with Homarr failed: {}", failure.describe());
+    state.auth_failure = Some(failure);
+    if let Err(e) = state.save(&config.state_file) {
+        warn!("Failed to record authentication failure in state: {}", e);
+    }
+}
 
-    info!("API key rotation complete, permanent key saved to state");
+/// Forget a recorded authentication failure once a key works again
+fn clear_auth_failure(config: &Config, state: &mut state::State) -> Result<()> {
+    if state.auth_failure.take().is_some() {
+        info!("Authentication with Homarr works again");
+        state.save(&config.state_file)?;
+    }
     Ok(())
 }
 
@@ -282,10 +466,13 @@ async fn run_setup(config: &Config) -> Result<()> {
     let branding = branding::BrandingConfig::load(&config.branding_file)?;
 
     // Create Homarr client
-    let mut client = homarr::HomarrClient::new(&config.homarr_url)?;
+    let mut client = homarr::HomarrClient::new(
+        &config.homarr_url,
+        retry::RequestPolicy::from_config(config),
+    )?;
 
     // Load state
-    let mut state = state::State::load(&config.state_file).unwrap_or_default();
+    let mut state = state::State::load(&config.state_file)?;
 
     // Ensure we have a valid API key (rotate from bootstrap if needed)
     ensure_authenticated(&mut client, config, &mut state).await?;
@@ -357,39 +544,56 @@ fn sync_authelia_credentials(
     Ok(())
 }
 
-async fn check_status(config: &Config) -> Result<()> {
-    let state = state::State::load(&config.state_file).unwrap_or_default();
-
-    if state.first_boot_completed {
-        println!("Status: First-boot setup completed");
-        println!(
-            "Authelia sync: {}",
-            if state.authelia_sync_completed {
-                "completed"
-            } else {
-                "not completed"
+async fn check_status(
+    config: &Config,
+    format: OutputFormat,
+    live: bool,
+    drift: bool,
+) -> Result<()> {
+    let mut state = state::State::load_readonly(&config.state_file).unwrap_or_default();
+
+    let live_status = if live {
+        Some(status::live_status(config, &state).await)
+    } else {
+        None
+    };
+    let drift_report = if drift {
+        Some(status::drift_status(config, &mut state).await)
+    } else {
+        None
+    };
+
+    match format {
+        OutputFormat::Text => {
+            print!("{}", status::render_text(&state, live_status.as_ref()));
+            if let Some(drift_report) = drift_report {
+                print!("{}", status::render_drift_text(&drift_report));
+            }
+        }
+        OutputFormat::Json | OutputFormat::Yaml => {
+            let mut report = status::state_report(&state);
+            if let Some(live_status) = live_status {
+                report["live"] = serde_json::to_value(live_status)?;
+            }
+            if let Some(drift_report) = drift_report {
+                report["drift"] = serde_json::to_value(drift_report)?;
+            }
+            match format {
+                OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+                _ => print!("{}", to_yaml(&report)?),
             }
-        );
-        println!("Last sync: {:?}", state.last_sync);
-        println!("Registered apps: {}", state.discovered_apps.len());
-        for (url, app) in &state.discovered_apps {
-            let container_info = if app.container_id.is_empty() {
-                "external".to_string()
-            } else {
-                format!(
-                    "container: {}",
-                    &app.container_id[..12.min(app.container_id.len())]
-                )
-            };
-            println!("  - {} ({}) [{}]", app.name, url, container_info);
         }
-    } else {
-        println!("Status: First-boot setup pending");
     }
 
     Ok(())
 }
 
+/// Serialize a report as YAML
+fn to_yaml<T: serde::Serialize>(value: &T) -> Result<String> {
+    serde_yaml::to_string(value)
+        .map_err(|e| AdapterError::Config(format!("Failed to serialize YAML: {}", e)))
+}
+
 /// Reset adapter state to initial values
 ///
 /// Removes the state file, clearing:
@@ -425,15 +629,20 @@ async fn run_watch(config: &Config) -> Result<()> {
             "Waiting {} seconds for Homarr to start...",
             config.startup_delay
         );
-        sleep(Duration::from_secs(config.startup_delay)).await;
+        sleep_with_watchdog(Duration::from_secs(config.startup_delay)).await;
+    }
+
+    // Start the health endpoint before anything can fail, so it reports why
+    let health: health::SharedHealth = Default::default();
+    if let Some(ref listen) = config.health_listen {
+        let addr = health::ListenAddr::parse(listen)?;
+        let listener = health::Listener::bind(&addr).await?;
+        info!("Serving health endpoint on {}", listen);
+        tokio::spawn(listener.serve(health.clone()));
     }
 
     // Connect to Docker
-    let docker = Docker::connect_with_socket(
-        &config.docker_socket,
-        120, // timeout in seconds
-        bollard::API_DEFAULT_VERSION,
-    )?;
+    let docker = docker::connect(&config.docker_socket)?;
 
     // Verify Docker connection
     match docker.ping().await {
@@ -444,54 +653,146 @@ async fn run_watch(config: &Config) -> Result<()> {
         }
     }
 
-    // Run initial sync with retry
+    // Run initial sync, retrying with backoff until it succeeds
+    let backoff = retry::sync_backoff(config);
+    let mut failures = 0;
     loop {
-        match run_sync(config).await {
+        systemd::watchdog();
+        match watch_sync(config, &docker, &health).await {
             Ok(_) => {
                 info!("Initial sync completed successfully");
                 break;
             }
             Err(e) => {
-                warn!("Initial sync failed: {}. Retrying in 10 seconds...", e);
-                sleep(Duration::from_secs(10)).await;
+                failures += 1;
+                let delay = backoff.delay(failures);
+                warn!(
+                    "Initial sync failed (attempt {}): {}. Retrying in {} seconds...",
+                    failures,
+                    e,
+                    delay.as_secs()
+                );
+                systemd::status(&format!("Initial sync failed, retrying: {}", e));
+                sleep_with_watchdog(delay).await;
             }
         }
     }
 
-    // Start watching Docker events and periodic sync
+    // Tell systemd (Type=notify) that the service is up
+    systemd::ready("Initial sync complete, watching for changes");
+
+    // Start watching Docker events, the registry directory and periodic sync
     info!(
-        "Watching for Docker events, periodic sync every {} seconds",
+        "Watching for Docker events and registry changes, periodic sync every {} seconds",
         config.sync_interval
     );
-    watch_loop(config, &docker).await
+    watch_loop(config, &docker, &health).await
+}
+
+/// Sleep while keeping the systemd watchdog fed
+async fn sleep_with_watchdog(duration: Duration) {
+    let step = systemd::watchdog_interval().unwrap_or(duration);
+    let deadline = Instant::now() + duration;
+    while Instant::now() < deadline {
+        systemd::watchdog();
+        sleep_until(deadline.min(Instant::now() + step)).await;
+    }
 }
 
-/// Main watch loop that handles Docker events and periodic syncs
-async fn watch_loop(config: &Config, docker: &Docker) -> Result<()> {
+/// Run a sync from watch mode and update the health report
+///
+/// Under a systemd watchdog, the sync and key rotation must finish within
+/// three quarters of `WatchdogSec=`, so a slow Homarr fails the sync instead
+/// of getting the service restarted. The health probe after it has short
+/// timeouts of its own that fit in the rest.
+async fn watch_sync(config: &Config, docker: &Docker, health: &health::SharedHealth) -> Result<()> {
+    systemd::watchdog();
+    let deadline = systemd::watchdog_interval().map(|ping| Instant::now() + ping * 3 / 2);
+
+    let started = std::time::Instant::now();
+    let result = with_deadline(deadline, "Sync", run_sync(config)).await;
+    metrics::record_sync(started.elapsed(), result.is_ok());
+    health.lock().unwrap().record_sync(&result);
+
+    let now = chrono::Utc::now().format("%Y-%m-%d %H:%M:%S UTC");
+    match &result {
+        Ok(()) => systemd::status(&format!("Last sync succeeded at {}", now)),
+        Err(e) => systemd::status(&format!("Last sync failed at {}: {}", now, e)),
+    };
+
+    // Rotate the API key between syncs, never while one is using it
+    if result.is_ok() {
+        if let Err(e) =
+            with_deadline(deadline, "API key rotation", apikey::rotate_if_due(config)).await
+        {
+            warn!("API key rotation failed: {}", e);
+        }
+    }
+
+    health::refresh(health, config, docker).await;
+    systemd::watchdog();
+    result
+}
+
+/// Run `task`, failing it if it is still running at `deadline`
+async fn with_deadline(
+    deadline: Option<Instant>,
+    what: &str,
+    task: impl std::future::Future<Output = Result<()>>,
+) -> Result<()> {
+    let Some(deadline) = deadline else {
+        return task.await;
+    };
+    tokio::time::timeout_at(deadline, task)
+        .await
+        .unwrap_or_else(|_| {
+            Err(AdapterError::HomarrApi(format!(
+                "{} did not finish before the watchdog deadline",
+                what
+            )))
+        })
+}
+
+/// Main watch loop that handles Docker events, registry changes and periodic syncs
+///
+/// Docker events and registry changes schedule a sync instead of running one
+/// directly, so a burst of triggers is coalesced into a single sync.
+async fn watch_loop(config: &Config, docker: &Docker, health: &health::SharedHealth) -> Result<()> {
     let mut sync_timer = interval(Duration::from_secs(config.sync_interval));
     // Skip the first immediate tick
     sync_timer.tick().await;
 
-    // Set up Docker event stream with filter for container events
-    let mut filters = HashMap::new();
-    filters.insert("type", vec!["container"]);
-    filters.insert("event", vec!["start", "stop", "die", "destroy"]);
+    // Watch the registry directory. If it can't be watched yet (e.g. it doesn't exist),
+    // changes are picked up by the periodic sync, which also retries watching it.
+    let mut registry_watcher = match watch::RegistryWatcher::new(&config.registry_dir) {
+        Ok(watcher) => Some(watcher),
+        Err(e) => {
+            warn!(
+                "Cannot watch registry directory {}: {}, retrying before each periodic sync",
+                config.registry_dir, e
+            );
+            None
+        }
+    };
 
-    loop {
-        // Create a fresh event stream for this iteration
-        let options = EventsOptions {
-            since: None,
-            until: None,
-            filters: filters.clone(),
-        };
-        let mut events = docker.events(Some(options));
+    let mut pending = watch::PendingSync::new(watch::MAX_SYNC_DELAY);
+    let mut referenced = watch::referenced_containers(config);
 
+    // Keep one event stream open so events arriving during a sync are not lost
+    let mut events = container_events(docker);
+
+    // Ping the systemd watchdog from this loop, so a hung sync stops the pings
+    let mut watchdog = systemd::watchdog_interval().map(interval);
+
+    loop {
         tokio::select! {
             // Handle Docker events
-            Some(event_result) = events.next() => {
+            event_result = events.next() => {
                 match event_result {
-                    Ok(event) => {
+                    Some(Ok(event)) => {
+                        health.lock().unwrap().docker_connected = true;
                         let action = event.action.as_deref().unwrap_or("unknown");
+                        metrics::record_docker_event(action);
                         let actor = event.actor.as_ref();
                         let container_name = actor
                             .and_then(|a| a.attributes.as_ref())
@@ -499,49 +800,83 @@ async fn watch_loop(config: &Config, docker: &Docker) -> Result<()> {
                             .map(|s| s.as_str())
                             .unwrap_or("unknown");
 
-                        info!("Docker event: {} container '{}'", action, container_name);
-
-                        // Brief delay to let container fully start/stop
-                        sleep(Duration::from_secs(2)).await;
-
-                        // Trigger sync
-                        if let Err(e) = run_sync(config).await {
-                            warn!("Sync failed after Docker event: {}", e);
+                        if watch::is_relevant_event(&event, &referenced) {
+                            info!("Docker event: {} container '{}'", action, container_name);
+                            pending.schedule(Instant::now(), watch::DOCKER_DEBOUNCE);
+                        } else {
+                            debug!("Ignoring Docker event: {} container '{}'", action, container_name);
                         }
                     }
-                    Err(e) => {
+                    Some(Err(e)) => {
                         warn!("Docker event stream error: {}. Reconnecting...", e);
+                        health.lock().unwrap().docker_connected = false;
                         sleep(Duration::from_secs(5)).await;
+                        events = container_events(docker);
+                        // Events may have been missed while disconnected
+                        pending.schedule(Instant::now(), watch::DOCKER_DEBOUNCE);
                     }
+                    None => {
+                        warn!("Docker event stream ended. Reconnecting...");
+                        health.lock().unwrap().docker_connected = false;
+                        sleep(Duration::from_secs(5)).await;
+                        events = container_events(docker);
+                        pending.schedule(Instant::now(), watch::DOCKER_DEBOUNCE);
+                    }
+                }
+            }
+
+            // Registry files created, modified or deleted
+            Some(path) = watch::next_registry_change(&mut registry_watcher) => {
+                debug!("Registry change: {:?}", path);
+                pending.schedule(Instant::now(), watch::REGISTRY_DEBOUNCE);
+            }
+
+            // Triggers have settled
+            _ = sleep_until(pending.deadline().unwrap_or_else(Instant::now)), if pending.deadline().is_some() => {
+                let triggers = pending.take();
+                info!("Syncing after {} change(s)", triggers);
+                if let Err(e) = watch_sync(config, docker, health).await {
+                    warn!("Sync failed: {}", e);
+                }
+                referenced = watch::referenced_containers(config);
+                sync_timer.reset();
+            }
+
+            // systemd watchdog
+            _ = async {
+                match watchdog.as_mut() {
+                    Some(timer) => timer.tick().await,
+                    None => std::future::pending().await,
                 }
+            } => {
+                systemd::watchdog();
             }
 
             // Periodic sync timer
             _ = sync_timer.tick() => {
                 debug!("Periodic sync triggered");
-                if let Err(e) = run_sync(config).await {
+                watch::watch_registry(&mut registry_watcher, &config.registry_dir);
+                if let Err(e) = watch_sync(config, docker, health).await {
                     warn!("Periodic sync failed: {}", e);
                 }
+                referenced = watch::referenced_containers(config);
             }
         }
     }
 }
 
-/// Get the current list of running containers (for debugging)
-#[allow(dead_code)]
-async fn list_containers(docker: &Docker) -> Result<Vec<String>> {
-    let options = ListContainersOptions::<String> {
-        all: false,
-        ..Default::default()
-    };
-
-    let containers = docker.list_containers(Some(options)).await?;
-    let names: Vec<String> = containers
-        .iter()
-        .filter_map(|c| c.names.as_ref())
-        .flat_map(|names| names.iter())
-        .map(|name| name.trim_start_matches('/').to_string())
-        .collect();
+/// Open a Docker event stream for container lifecycle events
+fn container_events(
+    docker: &Docker,
+) -> impl futures_util::Stream<Item = std::result::Result<EventMessage, bollard::errors::Error>> + '_
+{
+    let mut filters = HashMap::new();
+    filters.insert("type", vec!["container"]);
+    filters.insert("event", vec!["start", "stop", "die", "destroy"]);
 
-    Ok(names)
+    docker.events(Some(EventsOptions {
+        since: None,
+        until: None,
+        filters,
+    }))
 }
@@ -0,0 +1,192 @@
+//! Prometheus metrics
+//!
+//! Metrics are collected process-wide and served on `/metrics` by the health
+//! listener in watch mode. One-shot commands record them too, but nothing
+//! exports them.
+
+use prometheus::{
+    Encoder, Histogram, HistogramOpts, IntCounterVec, IntGauge, Opts, Registry, TextEncoder,
+};
+use std::sync::LazyLock;
+use std::time::Duration;
+
+/// Prefix of all metric names
+const NAMESPACE: &str = "homarr_adapter";
+
+/// Content type of `render()` output
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+struct Metrics {
+    registry: Registry,
+    sync_duration: Histogram,
+    syncs: IntCounterVec,
+    homarr_requests: IntCounterVec,
+    docker_events: IntCounterVec,
+    registry_apps: IntGauge,
+    visible_apps: IntGauge,
+}
+
+static METRICS: LazyLock<Metrics> = LazyLock::new(|| {
+    let registry = Registry::new();
+
+    let sync_duration = Histogram::with_opts(
+        HistogramOpts::new("sync_duration_seconds", "Duration of sync cycles")
+            .namespace(NAMESPACE)
+            .buckets(vec![0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0]),
+    )
+    .expect("valid metric");
+    let syncs = IntCounterVec::new(
+        Opts::new("syncs_total", "Sync cycles by outcome").namespace(NAMESPACE),
+        &["outcome"],
+    )
+    .expect("valid metric");
+    let homarr_requests = IntCounterVec::new(
+        Opts::new(
+            "homarr_requests_total",
+            "Homarr API requests by tRPC procedure and HTTP status",
+        )
+        .namespace(NAMESPACE),
+        &["procedure", "status"],
+    )
+    .expect("valid metric");
+    let docker_events = IntCounterVec::new(
+        Opts::new("docker_events_total", "Docker container events by action").namespace(NAMESPACE),
+        &["action"],
+    )
+    .expect("valid metric");
+    let registry_apps = IntGauge::with_opts(
+        Opts::new(
+            "registry_apps",
+            "Apps in the registry, including container labels",
+        )
+        .namespace(NAMESPACE),
+    )
+    .expect("valid metric");
+    let visible_apps = IntGauge::with_opts(
+        Opts::new(
+            "visible_apps",
+            "Registry apps that are visible on the dashboard",
+        )
+        .namespace(NAMESPACE),
+    )
+    .expect("valid metric");
+
+    registry
+        .register(Box::new(sync_duration.clone()))
+        .expect("unique metric");
+    registry
+        .register(Box::new(syncs.clone()))
+        .expect("unique metric");
+    registry
+        .register(Box::new(homarr_requests.clone()))
+        .expect("unique metric");
+    registry
+        .register(Box::new(docker_events.clone()))
+        .expect("unique metric");
+    registry
+        .register(Box::new(registry_apps.clone()))
+        .expect("unique metric");
+    registry
+        .register(Box::new(visible_apps.clone()))
+        .expect("unique metric");
+
+    Metrics {
+        registry,
+        sync_duration,
+        syncs,
+        homarr_requests,
+        docker_events,
+        registry_apps,
+        visible_apps,
+    }
+});
+
+/// Record a finished sync cycle
+pub fn record_sync(duration: Duration, success: bool) {
+    METRICS.sync_duration.observe(duration.as_secs_f64());
+    let outcome = if success { "success" } else { "failure" };
+    METRICS.syncs.with_label_values(&[outcome]).inc();
+}
+
+/// Record a Homarr API request
+///
+/// `status` is `None` if no response was received (connection error, timeout).
+pub fn record_homarr_request(url: &str, status: Option<u16>) {
+    let status = status.map_or_else(|| "error".to_string(), |s| s.to_string());
+    METRICS
+        .homarr_requests
+        .with_label_values(&[procedure(url), status.as_str()])
+        .inc();
+}
+
+/// Record a Docker container event
+pub fn record_docker_event(action: &str) {
+    METRICS.docker_events.with_label_values(&[action]).inc();
+}
+
+/// Record how many apps the registry has and how many are visible
+pub fn set_app_counts(registry: usize, visible: usize) {
+    METRICS.registry_apps.set(registry as i64);
+    METRICS.visible_apps.set(visible as i64);
+}
+
+/// Render all metrics in the Prometheus text format
+pub fn render() -> String {
+    let mut buf = Vec::new();
+    if let Err(e) = TextEncoder::new().encode(&METRICS.registry.gather(), &mut buf) {
+        tracing::warn!("Failed to encode metrics: {}", e);
+    }
+    String::from_utf8(buf).unwrap_or_default()
+}
+
+/// tRPC procedure name of a Homarr API URL, or its path for other endpoints
+pub fn procedure(url: &str) -> &str {
+    let path = url
+        .split_once("://")
+        .map_or(url, |(_, rest)| rest.find('/').map_or("/", |i| &rest[i..]));
+    let path = path.split('?').next().unwrap_or(path);
+    path.strip_prefix("/api/trpc/").unwrap_or(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_procedure() {
+        assert_eq!(
+            procedure("http://localhost:7575/api/trpc/board.getBoardByName?input=%7B%7D"),
+            "board.getBoardByName"
+        );
+        assert_eq!(
+            procedure("http://localhost:7575/api/trpc/app.create"),
+            "app.create"
+        );
+        assert_eq!(
+            procedure("http://localhost:7575/api/auth/callback/credentials"),
+            "/api/auth/callback/credentials"
+        );
+    }
+
+    #[test]
+    fn test_render_includes_recorded_metrics() {
+        record_sync(Duration::from_millis(300), true);
+        record_homarr_request("http://localhost:7575/api/trpc/app.update", Some(500));
+        record_homarr_request("http://localhost:7575/api/trpc/app.update", None);
+        record_docker_event("start");
+        set_app_counts(5, 4);
+
+        let text = render();
+        assert!(text.contains("homarr_adapter_sync_duration_seconds_count"));
+        assert!(text.contains("homarr_adapter_syncs_total{outcome=\"success\"}"));
+        assert!(text.contains(
+            "homarr_adapter_homarr_requests_total{procedure=\"app.update\",status=\"500\"} 1"
+        ));
+        assert!(text.contains(
+            "homarr_adapter_homarr_requests_total{procedure=\"app.update\",status=\"error\"} 1"
+        ));
+        assert!(text.contains("homarr_adapter_docker_events_total{action=\"start\"}"));
+        assert!(text.contains("homarr_adapter_registry_apps 5"));
+        assert!(text.contains("homarr_adapter_visible_apps 4"));
+    }
+}
@@ -19,6 +19,12 @@ pub const DEFAULT_REGISTRY_DIR: &str = "/etc/halos/webapps.d";
 #[derive(Debug, Clone, Deserialize)]
 #[allow(dead_code)]
 pub struct AppDefinition {
+    /// Stable identity of the app, kept when its name or URL change
+    /// (default: the registry file name without `.toml`, or the container
+    /// name for apps discovered from labels)
+    #[serde(default)]
+    pub id: String,
+
     /// Display name for the app
     pub name: String,
 
@@ -106,14 +112,24 @@ impl Default for LayoutConfig {
     }
 }
 
-/// Loaded registry entry with source file path
+/// Where an app definition came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AppSource {
+    /// Static TOML file in the registry directory
+    File(PathBuf),
+
+    /// Running Docker container with `homarr.*` labels (container name)
+    Container(String),
+}
+
+/// Loaded registry entry with its source
 #[derive(Debug, Clone)]
 #[allow(dead_code)]
 pub struct RegistryEntry {
-    /// Source file path
-    pub file_path: PathBuf,
+    /// Where the definition was loaded from
+    pub source: AppSource,
 
-    /// App definition from the file
+    /// App definition from the file or container labels
     pub app: AppDefinition,
 }
 
@@ -184,7 +200,7 @@ pub fn load_all_apps<P: AsRef<Path>>(registry_dir: P) -> Result<Vec<RegistryEntr
                 Ok(app) => {
                     tracing::debug!("Loaded app '{}' from {:?}", app.name, path);
                     entries.push(RegistryEntry {
-                        file_path: path,
+                        source: AppSource::File(path),
                         app,
                     });
                 }
@@ -212,29 +228,128 @@ pub fn load_all_apps<P: AsRef<Path>>(registry_dir: P) -> Result<Vec<RegistryEntr
 fn load_app_file<P: AsRef<Path>>(path: P) -> Result<AppDefinition> {
     let path = path.as_ref();
     let contents = fs::read_to_string(path)?;
-    let app: AppDefinition = toml::from_str(&contents)?;
+    let mut app: AppDefinition = toml::from_str(&contents)?;
+
+    if app.id.is_empty() {
+        app.id = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+    }
+
+    validate_app(&app, &format!("{:?}", path))?;
+
+    Ok(app)
+}
+
+/// Validate required fields of an app definition
+///
+/// `origin` describes where the definition came from and is used in error messages.
+pub fn validate_app(app: &AppDefinition, origin: &str) -> Result<()> {
+    // IDs are kept apart from URLs, which state files written before IDs
+    // existed use as keys
+    if app.id.is_empty() || app.id.contains([':', '/']) {
+        return Err(AdapterError::Config(format!(
+            "Invalid app ID '{}' in {}: must be non-empty without ':' or '/'",
+            app.id, origin
+        )));
+    }
 
-    // Validate required fields
     if app.name.is_empty() {
         return Err(AdapterError::Config(format!(
-            "App name is empty in {:?}",
-            path
+            "App name is empty in {}",
+            origin
         )));
     }
 
     if app.url.is_empty() {
         return Err(AdapterError::Config(format!(
-            "App URL is empty in {:?}",
-            path
+            "App URL is empty in {}",
+            origin
         )));
     }
 
     // Validate URL format
     Url::parse(&app.url).map_err(|e| {
-        AdapterError::Config(format!("Invalid URL '{}' in {:?}: {}", app.url, path, e))
+        AdapterError::Config(format!("Invalid URL '{}' in {}: {}", app.url, origin, e))
     })?;
 
-    Ok(app)
+    Ok(())
+}
+
+/// Merge registry file entries with apps discovered from container labels
+///
+/// Registry files take precedence: a discovered app whose ID or URL is
+/// already defined by a registry file is dropped. IDs must be unique, so of
+/// two registry files with the same ID only the first is kept. The result is
+/// sorted by priority.
+pub fn merge_entries(
+    registry: Vec<RegistryEntry>,
+    discovered: Vec<RegistryEntry>,
+) -> Vec<RegistryEntry> {
+    let mut merged: Vec<RegistryEntry> = Vec::new();
+
+    for entry in registry {
+        if merged.iter().any(|e| e.app.id == entry.app.id) {
+            tracing::warn!(
+                "Skipping app '{}' from {:?}: ID '{}' is already used",
+                entry.app.name,
+                entry.source,
+                entry.app.id
+            );
+            continue;
+        }
+        merged.push(entry);
+    }
+
+    for entry in discovered {
+        if merged
+            .iter()
+            .any(|e| e.app.id == entry.app.id || e.app.url == entry.app.url)
+        {
+            tracing::debug!(
+                "Skipping discovered app '{}': ID '{}' or URL {} is already defined in the registry",
+                entry.app.name,
+                entry.app.id,
+                entry.app.url
+            );
+            continue;
+        }
+        merged.push(entry);
+    }
+
+    // Stable sort keeps registry entries ahead of discovered ones at equal priority
+    merged.sort_by_key(|e| e.app.layout.priority);
+    merged
+}
+
+/// List the categories used by the given apps with their lowest priority
+///
+/// Categories are matched case-insensitively and returned in priority order
+/// (ties broken by name).
+pub fn categories_by_priority(entries: &[&RegistryEntry]) -> Vec<(String, u8)> {
+    let mut categories: Vec<(String, u8)> = Vec::new();
+
+    for entry in entries {
+        let Some(category) = entry.app.category.as_deref() else {
+            continue;
+        };
+        let priority = entry.app.priority();
+        match categories
+            .iter_mut()
+            .find(|(name, _)| name.eq_ignore_ascii_case(category))
+        {
+            // The spelling of the highest-priority app wins
+            Some(existing) if priority < existing.1 => {
+                *existing = (category.to_string(), priority);
+            }
+            Some(_) => {}
+            None => categories.push((category.to_string(), priority)),
+        }
+    }
+
+    categories.sort_by(|a, b| a.1.cmp(&b.1).then_with(|| a.0.cmp(&b.0)));
+    categories
 }
 
 /// Get apps as a HashMap keyed by URL (for deduplication)
@@ -269,6 +384,7 @@ url = "http://localhost:8080"
 
         let entries = load_all_apps(dir.path()).unwrap();
         assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].app.id, "test-app"); // file stem
         assert_eq!(entries[0].app.name, "Test App");
         assert_eq!(entries[0].app.url, "http://localhost:8080");
         assert_eq!(entries[0].app.priority(), 50); // default
@@ -284,6 +400,7 @@ url = "http://localhost:8080"
             dir.path(),
             "signalk",
             r#"
+id = "signalk-server"
 name = "Signal K"
 url = "http://test.example.local:3000"
 description = "Marine data server"
@@ -306,6 +423,7 @@ y_offset = 0
         assert_eq!(entries.len(), 1);
 
         let app = &entries[0].app;
+        assert_eq!(app.id, "signalk-server");
         assert_eq!(app.name, "Signal K");
         assert_eq!(app.priority(), 25);
         assert!(app.is_container());
@@ -540,4 +658,145 @@ url = "http://localhost:8082"
         assert!(!hidden_app.app.is_visible());
         assert!(!default_app.app.is_visible()); // default is false
     }
+
+    fn test_entry(name: &str, url: &str, priority: u8, source: AppSource) -> RegistryEntry {
+        RegistryEntry {
+            source,
+            app: AppDefinition {
+                id: name.to_lowercase().replace(' ', "-"),
+                name: name.to_string(),
+                url: url.to_string(),
+                description: None,
+                icon_url: None,
+                category: None,
+                visible: true,
+                app_type: AppType::default(),
+                ping_url: None,
+                layout: LayoutConfig {
+                    priority,
+                    ..Default::default()
+                },
+            },
+        }
+    }
+
+    #[test]
+    fn test_categories_by_priority() {
+        let file = || AppSource::File(PathBuf::from("/etc/halos/webapps.d/app.toml"));
+        let mut marine = test_entry("Signal K", "http://localhost:1", 25, file());
+        marine.app.category = Some("Marine".to_string());
+        let mut marine_low = test_entry("OpenCPN", "http://localhost:2", 60, file());
+        marine_low.app.category = Some("marine".to_string());
+        let mut system = test_entry("Cockpit", "http://localhost:3", 30, file());
+        system.app.category = Some("System".to_string());
+        let uncategorized = test_entry("Other", "http://localhost:4", 5, file());
+
+        let entries = vec![&marine_low, &system, &uncategorized, &marine];
+        let categories = categories_by_priority(&entries);
+
+        assert_eq!(
+            categories,
+            vec![("Marine".to_string(), 25), ("System".to_string(), 30)]
+        );
+    }
+
+    #[test]
+    fn test_load_records_file_source() {
+        let dir = TempDir::new().unwrap();
+        create_test_app_file(
+            dir.path(),
+            "test-app",
+            r#"
+name = "Test App"
+url = "http://localhost:8080"
+"#,
+        );
+
+        let entries = load_all_apps(dir.path()).unwrap();
+        assert_eq!(
+            entries[0].source,
+            AppSource::File(dir.path().join("test-app.toml"))
+        );
+    }
+
+    #[test]
+    fn test_merge_entries_registry_wins_on_url() {
+        let registry = vec![test_entry(
+            "Signal K",
+            "http://localhost:3000",
+            25,
+            AppSource::File(PathBuf::from("/etc/halos/webapps.d/signalk.toml")),
+        )];
+        let discovered = vec![test_entry(
+            "signalk-from-labels",
+            "http://localhost:3000",
+            50,
+            AppSource::Container("signalk-server".to_string()),
+        )];
+
+        let merged = merge_entries(registry, discovered);
+        assert_eq!(merged.len(), 1);
+        assert_eq!(merged[0].app.name, "Signal K");
+    }
+
+    #[test]
+    fn test_invalid_id_rejected() {
+        let dir = TempDir::new().unwrap();
+        create_test_app_file(
+            dir.path(),
+            "app",
+            r#"
+id = "http://localhost:8080"
+name = "App"
+url = "http://localhost:8080"
+"#,
+        );
+
+        let entries = load_all_apps(dir.path()).unwrap();
+        assert!(entries.is_empty());
+    }
+
+    #[test]
+    fn test_merge_entries_unique_ids() {
+        let file = |name: &str| {
+            AppSource::File(PathBuf::from(format!("/etc/halos/webapps.d/{}.toml", name)))
+        };
+        let mut duplicate = test_entry("Signal K", "http://localhost:3001", 30, file("other"));
+        duplicate.app.id = "signal-k".to_string();
+        let registry = vec![
+            test_entry("Signal K", "http://localhost:3000", 25, file("signal-k")),
+            duplicate,
+        ];
+        let discovered = vec![test_entry(
+            "Signal K",
+            "http://localhost:3002",
+            50,
+            AppSource::Container("signal-k".to_string()),
+        )];
+
+        let merged = merge_entries(registry, discovered);
+        assert_eq!(merged.len(), 1);
+        assert_eq!(merged[0].app.url, "http://localhost:3000");
+    }
+
+    #[test]
+    fn test_merge_entries_sorted_by_priority() {
+        let registry = vec![test_entry(
+            "Registry App",
+            "http://localhost:1",
+            60,
+            AppSource::File(PathBuf::from("/etc/halos/webapps.d/app.toml")),
+        )];
+        let discovered = vec![test_entry(
+            "Label App",
+            "http://localhost:2",
+            50,
+            AppSource::Container("label-app".to_string()),
+        )];
+
+        let merged = merge_entries(registry, discovered);
+        assert_eq!(merged.len(), 2);
+        assert_eq!(merged[0].app.name, "Label App");
+        assert_eq!(merged[1].app.name, "Registry App");
+    }
 }
@@ -0,0 +1,144 @@
+//! Retry policies
+//!
+//! Homarr requests get connect and request timeouts, and idempotent ones are
+//! retried with exponential backoff on connection errors, timeouts and 5xx
+//! responses. Watch mode retries its initial sync with a backoff as well.
+
+use std::time::Duration;
+
+use crate::config::Config;
+
+/// Longest wait between two attempts of a Homarr request
+const MAX_REQUEST_RETRY_DELAY: Duration = Duration::from_secs(10);
+
+/// Longest time a health probe may wait for Homarr
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Exponential backoff: `initial`, doubling with each retry, up to `max`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Backoff {
+    pub initial: Duration,
+    pub max: Duration,
+}
+
+impl Backoff {
+    /// Delay before the given retry (1 for the first)
+    pub fn delay(&self, retry: u32) -> Duration {
+        let factor = 2u32.saturating_pow(retry.saturating_sub(1));
+        self.initial.saturating_mul(factor).min(self.max)
+    }
+}
+
+/// Timeouts and retries for Homarr API requests
+#[derive(Debug, Clone)]
+pub struct RequestPolicy {
+    /// Time allowed to establish a connection
+    pub connect_timeout: Duration,
+
+    /// Time allowed for a whole request, including the response body
+    pub timeout: Duration,
+
+    /// Retries after the first attempt, for idempotent requests only
+    pub max_retries: u32,
+
+    pub backoff: Backoff,
+}
+
+impl Default for RequestPolicy {
+    fn default() -> Self {
+        Self::from_config(&Config::default())
+    }
+}
+
+impl RequestPolicy {
+    /// The policy set by the `homarr_*` config options
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            connect_timeout: Duration::from_secs(config.homarr_connect_timeout),
+            timeout: Duration::from_secs(config.homarr_request_timeout),
+            max_retries: config.homarr_max_retries,
+            backoff: Backoff {
+                initial: Duration::from_millis(config.homarr_retry_delay_ms),
+                max: MAX_REQUEST_RETRY_DELAY,
+            },
+        }
+    }
+
+    /// A single short attempt, for health probes that must not hold up the
+    /// watch loop
+    pub fn probe(config: &Config) -> Self {
+        let policy = Self::from_config(config);
+        Self {
+            connect_timeout: policy.connect_timeout.min(PROBE_TIMEOUT),
+            timeout: policy.timeout.min(PROBE_TIMEOUT),
+            max_retries: 0,
+            ..policy
+        }
+    }
+}
+
+/// Backoff for retrying the initial sync in watch mode
+///
+/// Starts at `sync_retry_delay` and never waits longer than the periodic
+/// sync interval.
+pub fn sync_backoff(config: &Config) -> Backoff {
+    let initial = Duration::from_secs(config.sync_retry_delay);
+    Backoff {
+        initial,
+        max: Duration::from_secs(config.sync_interval).max(initial),
+    }
+}
+
+/// Check if a failed request may succeed when sent again
+pub fn is_transient(result: &reqwest::Result<reqwest::Response>) -> bool {
+    match result {
+        Ok(response) => response.status().is_server_error(),
+        Err(e) => e.is_connect() || e.is_timeout() || e.is_request(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_doubles_up_to_max() {
+        let backoff = Backoff {
+            initial: Duration::from_millis(500),
+            max: Duration::from_secs(3),
+        };
+        let delays: Vec<_> = (1..=5).map(|retry| backoff.delay(retry)).collect();
+        assert_eq!(
+            delays,
+            vec![
+                Duration::from_millis(500),
+                Duration::from_secs(1),
+                Duration::from_secs(2),
+                Duration::from_secs(3),
+                Duration::from_secs(3),
+            ]
+        );
+        // No overflow after many retries
+        assert_eq!(backoff.delay(100), Duration::from_secs(3));
+    }
+
+    #[test]
+    fn test_probe_policy_is_short_and_not_retried() {
+        let policy = RequestPolicy::probe(&Config::default());
+        assert_eq!(policy.max_retries, 0);
+        assert_eq!(policy.timeout, PROBE_TIMEOUT);
+        assert!(policy.connect_timeout <= PROBE_TIMEOUT);
+    }
+
+    #[test]
+    fn test_sync_backoff_bounded_by_sync_interval() {
+        let config = Config {
+            sync_retry_delay: 10,
+            sync_interval: 60,
+            ..Default::default()
+        };
+        let backoff = sync_backoff(&config);
+        assert_eq!(backoff.delay(1), Duration::from_secs(10));
+        assert_eq!(backoff.delay(4), Duration::from_secs(60));
+    }
+}
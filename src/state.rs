@@ -4,12 +4,112 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::path::Path;
+use std::io::Write;
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+use std::path::{Path, PathBuf};
 
 use crate::error::{AdapterError, Result};
 
+/// Schema version written by this build
+pub const STATE_VERSION: &str = "1.3";
+
+/// A step that upgrades the state file from one schema version to the next
+///
+/// Migrations run on the raw JSON before it is parsed, so they can rename or
+/// re-key fields. `apply` returns a description of each change it made.
+struct Migration {
+    from: &'static str,
+    to: &'static str,
+    apply: fn(&mut serde_json::Value) -> Vec<String>,
+}
+
+/// Migrations in order; each one's `to` is the next one's `from`
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        from: "1.0",
+        to: "1.1",
+        apply: migrate_1_0_to_1_1,
+    },
+    Migration {
+        from: "1.1",
+        to: "1.2",
+        apply: migrate_1_1_to_1_2,
+    },
+    Migration {
+        from: "1.2",
+        to: "1.3",
+        apply: migrate_1_2_to_1_3,
+    },
+];
+
+/// 1.1 records Homarr app and board item IDs for tracked apps
+///
+/// They can't be derived from the old state; the next sync fills them in.
+fn migrate_1_0_to_1_1(value: &mut serde_json::Value) -> Vec<String> {
+    let tracked = value
+        .get("discovered_apps")
+        .and_then(|apps| apps.as_object())
+        .map_or(0, |apps| apps.len());
+    if tracked == 0 {
+        return vec![];
+    }
+    vec![format!(
+        "Homarr app and board item IDs of {} tracked app(s) will be recorded on the next sync",
+        tracked
+    )]
+}
+
+/// 1.2 keys apps by registry app ID instead of URL
+///
+/// The URL keys of tracked and pruned apps are copied into their `url`
+/// field. Mapping them to registry IDs needs the registry, so the keys are
+/// replaced by `adopt_app_ids()` on the next sync.
+fn migrate_1_1_to_1_2(value: &mut serde_json::Value) -> Vec<String> {
+    let mut changes = Vec::new();
+
+    for field in ["discovered_apps", "pruned_apps"] {
+        let Some(apps) = value.get_mut(field).and_then(|apps| apps.as_object_mut()) else {
+            continue;
+        };
+        for (url, app) in apps.iter_mut() {
+            if let Some(app) = app.as_object_mut() {
+                app.insert("url".to_string(), serde_json::Value::String(url.clone()));
+            }
+        }
+        if !apps.is_empty() {
+            changes.push(format!(
+                "recorded the URL of {} {} entr{}; they are keyed by app ID from the next sync",
+                apps.len(),
+                field,
+                if apps.len() == 1 { "y" } else { "ies" }
+            ));
+        }
+    }
+
+    changes
+}
+
+/// 1.3 can keep the API key outside the state file (`api_key_ref`)
+///
+/// Moving the key needs the config, so it is done by
+/// `apikey::migrate_storage()` when the adapter next authenticates.
+fn migrate_1_2_to_1_3(value: &mut serde_json::Value) -> Vec<String> {
+    if value.get("api_key").is_some_and(|key| !key.is_null()) {
+        vec!["the API key moves to the configured key storage on the next sync".to_string()]
+    } else {
+        vec![]
+    }
+}
+
+/// Check if a state key is an app URL from before apps had IDs
+///
+/// App IDs can't contain ':' (see `registry::validate_app`), URLs always do.
+fn is_legacy_key(key: &str) -> bool {
+    key.contains(':')
+}
+
 /// Persistent state for the adapter
-#[derive(Debug, Default, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct State {
     /// Schema version for migrations
     #[serde(default = "default_version")]
@@ -29,8 +129,28 @@ pub struct State {
     #[serde(default)]
     pub api_key: Option<String>,
 
+    /// Where the API key is kept if not in `api_key` (see `apikey::KeyStore`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key_ref: Option<String>,
+
+    /// When the API key was created (unknown for keys from older versions)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key_created_at: Option<DateTime<Utc>>,
+
+    /// IDs of replaced API keys that could not be deleted in Homarr yet
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub retired_api_key_ids: Vec<String>,
+
+    /// When Homarr was verified to reject the bootstrap API key after rotation
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bootstrap_key_revoked_at: Option<DateTime<Utc>>,
+
+    /// Why the last attempt to authenticate with Homarr failed, if it did
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth_failure: Option<AuthFailure>,
+
     /// Apps removed from specific boards (don't re-add to that board)
-    /// Key: board_id, Value: set of app URLs removed from that board
+    /// Key: board_id, Value: set of app IDs removed from that board
     #[serde(default)]
     pub removed_apps_by_board: HashMap<String, HashSet<String>>,
 
@@ -39,43 +159,308 @@ pub struct State {
     pub last_sync: Option<DateTime<Utc>>,
 
     /// Discovered apps and when they were added
+    /// Key: registry app ID
     #[serde(default)]
     pub discovered_apps: std::collections::HashMap<String, DiscoveredApp>,
+
+    /// Managed apps present on each board at the end of the last sync
+    /// Key: board_id, Value: set of app IDs seen on that board.
+    /// Compared with the next sync's board contents to detect tiles removed by the user.
+    #[serde(default)]
+    pub board_apps_seen: HashMap<String, HashSet<String>>,
+
+    /// Apps pruned because their registry entry or container disappeared
+    /// Key: registry app ID
+    #[serde(default)]
+    pub pruned_apps: HashMap<String, PrunedApp>,
+}
+
+/// Why the adapter could not authenticate with Homarr
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthFailure {
+    /// Homarr rejected the stored API key and there is no bootstrap key to
+    /// rotate from
+    KeyRejected,
+
+    /// Homarr rejected the bootstrap key (and the stored key, if any)
+    BootstrapRejected,
+}
+
+impl AuthFailure {
+    /// What an operator should know about the failure
+    pub fn describe(&self) -> &'static str {
+        match self {
+            Self::KeyRejected => "stored API key rejected by Homarr and no bootstrap key available",
+            Self::BootstrapRejected => "API key and bootstrap key rejected by Homarr",
+        }
+    }
+}
+
+/// Path next to the state file with an extra extension (e.g. `state.json.bak`)
+fn sibling(path: &Path, extension: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".");
+    name.push(extension);
+    PathBuf::from(name)
+}
+
+/// Write `contents` to an owner-only temp file next to `path` and sync it
+fn write_temp(path: &Path, contents: &[u8]) -> Result<PathBuf> {
+    let tmp = sibling(path, "tmp");
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(&tmp)?;
+    // mode() only applies to new files; a leftover temp file keeps its own
+    file.set_permissions(fs::Permissions::from_mode(0o600))?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    Ok(tmp)
+}
+
+/// Move a temp file from `write_temp()` into place and persist the rename
+fn commit(tmp: &Path, path: &Path) -> Result<()> {
+    fs::rename(tmp, path)?;
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::File::open(parent)?.sync_all()?;
+    }
+    Ok(())
+}
+
+/// Atomically replace a file with contents only its owner can read
+pub fn write_private(path: &Path, contents: &[u8]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let tmp = write_temp(path, contents)?;
+    commit(&tmp, path)
 }
 
 fn default_version() -> String {
+    // Files written before versioning was introduced
     "1.0".to_string()
 }
 
+impl Default for State {
+    fn default() -> Self {
+        Self {
+            version: STATE_VERSION.to_string(),
+            first_boot_completed: false,
+            authelia_sync_completed: false,
+            api_key: None,
+            api_key_ref: None,
+            api_key_created_at: None,
+            retired_api_key_ids: Vec::new(),
+            bootstrap_key_revoked_at: None,
+            auth_failure: None,
+            removed_apps_by_board: HashMap::new(),
+            last_sync: None,
+            discovered_apps: HashMap::new(),
+            board_apps_seen: HashMap::new(),
+            pruned_apps: HashMap::new(),
+        }
+    }
+}
+
+/// Parse a `major.minor` schema version
+fn parse_version(version: &str) -> Result<(u32, u32)> {
+    version
+        .split_once('.')
+        .and_then(|(major, minor)| Some((major.parse().ok()?, minor.parse().ok()?)))
+        .ok_or_else(|| AdapterError::State(format!("Invalid state version '{}'", version)))
+}
+
+/// Upgrade raw state JSON to `target` by running `migrations` step by step
+///
+/// Returns descriptions of the changes made. A state newer than `target` is
+/// refused rather than silently losing fields this build doesn't know about.
+fn migrate(
+    value: &mut serde_json::Value,
+    migrations: &[Migration],
+    target: &str,
+) -> Result<Vec<String>> {
+    let object = value
+        .as_object_mut()
+        .ok_or_else(|| AdapterError::State("State is not a JSON object".to_string()))?;
+    let mut version = match object.get("version") {
+        // Files saved before versioning held an empty version
+        Some(serde_json::Value::String(version)) if version.is_empty() => default_version(),
+        Some(serde_json::Value::String(version)) => version.clone(),
+        Some(other) => {
+            return Err(AdapterError::State(format!(
+                "Invalid state version {}",
+                other
+            )))
+        }
+        None => default_version(),
+    };
+
+    if parse_version(&version)? > parse_version(target)? {
+        return Err(AdapterError::StateVersion {
+            found: version,
+            supported: target.to_string(),
+        });
+    }
+
+    let mut changes = Vec::new();
+    while version != target {
+        let migration = migrations
+            .iter()
+            .find(|m| m.from == version)
+            .ok_or_else(|| {
+                AdapterError::State(format!("No migration from state version {}", version))
+            })?;
+
+        for change in (migration.apply)(value) {
+            changes.push(format!(
+                "{} -> {}: {}",
+                migration.from, migration.to, change
+            ));
+        }
+        version = migration.to.to_string();
+        value["version"] = serde_json::Value::String(version.clone());
+    }
+
+    Ok(changes)
+}
+
 /// Discovered app metadata stored in state.
-/// Note: The HashMap key is the app URL (stable identifier).
+/// Note: The HashMap key is the registry app ID (stable identifier), so a
+/// changed URL updates the app instead of adding a new one.
 /// Container ID is stored for reference but not used as key since it changes on container restart.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct DiscoveredApp {
     pub name: String,
+
+    /// App URL as of the last sync
+    #[serde(default)]
+    pub url: String,
+
     pub container_id: String,
     pub added_at: DateTime<Utc>,
+
+    /// ID of the app in Homarr's app registry
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub app_id: Option<String>,
+
+    /// Whether the adapter created the Homarr app, rather than adopting an
+    /// existing one with the same URL. Only created apps are deleted when
+    /// pruning.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub created_by_adapter: bool,
+
+    /// IDs of the app's tiles
+    /// Key: board_id, Value: board item ID
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub board_items: HashMap<String, String>,
+}
+
+/// Record of an app that was removed from Homarr by pruning
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PrunedApp {
+    pub name: String,
+    #[serde(default)]
+    pub url: String,
+    pub pruned_at: DateTime<Utc>,
 }
 
 impl State {
     /// Load state from file, returning default if file doesn't exist
+    ///
+    /// A state file that can't be parsed (e.g. truncated by a power loss) is
+    /// moved aside to `<file>.corrupt` and the backup written by the previous
+    /// save is used instead; so is a missing state file if a backup exists.
+    /// If the backup is unusable too, state is rebuilt from defaults so the
+    /// sync can still run. A file that can't be read (e.g. permissions) is an
+    /// error and is left in place.
     pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
         let path = path.as_ref();
+        let backup = sibling(path, "bak");
+
+        if path.exists() {
+            let e = match Self::read(path) {
+                Ok(state) => return Ok(state),
+                // Only unparsable contents are corrupt; a newer version or a
+                // read error (e.g. permissions) must not discard the file
+                Err(e @ AdapterError::State(_)) => e,
+                Err(e) => return Err(e),
+            };
+            tracing::warn!("Failed to load state file {}: {}", path.display(), e);
 
-        if !path.exists() {
+            let corrupt = sibling(path, "corrupt");
+            if let Err(e) = fs::rename(path, &corrupt) {
+                tracing::warn!("Failed to move aside corrupt state file: {}", e);
+            }
+        } else if !backup.exists() {
             return Ok(Self::default());
         }
 
-        let contents = fs::read_to_string(path)?;
-        let state: State = serde_json::from_str(&contents).map_err(|e| {
-            tracing::warn!("Failed to parse state file, using defaults: {}", e);
-            AdapterError::State(format!("Failed to parse state: {}", e))
-        })?;
+        if backup.exists() {
+            match Self::read(&backup) {
+                Ok(state) => {
+                    tracing::warn!("Restored state from backup {}", backup.display());
+                    return Ok(state);
+                }
+                Err(e) => tracing::warn!("Failed to load state backup: {}", e),
+            }
+        }
+
+        tracing::warn!("No usable state, starting with defaults");
+        Ok(Self::default())
+    }
+
+    /// Load state for reading only, leaving the state files untouched
+    ///
+    /// Like `load()`, falls back to the backup and then to defaults, but never
+    /// moves a corrupt file aside. For commands and probes that don't save
+    /// state; only the sync recovers the state files.
+    pub fn load_readonly<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+
+        match Self::read(path) {
+            Ok(state) => return Ok(state),
+            Err(e @ AdapterError::StateVersion { .. }) => return Err(e),
+            Err(e) if path.exists() => {
+                tracing::debug!("Failed to load state file {}: {}", path.display(), e)
+            }
+            Err(_) => {}
+        }
+
+        match Self::read(&sibling(path, "bak")) {
+            Ok(state) => Ok(state),
+            Err(e @ AdapterError::StateVersion { .. }) => Err(e),
+            Err(_) => Ok(Self::default()),
+        }
+    }
+
+    /// Read, parse and migrate a state file
+    ///
+    /// Unparsable contents are reported as `AdapterError::State`, failures to
+    /// read the file as `AdapterError::Io`.
+    fn read(path: &Path) -> Result<Self> {
+        let contents = String::from_utf8(fs::read(path)?)
+            .map_err(|e| AdapterError::State(format!("Failed to parse state: {}", e)))?;
+        let mut value: serde_json::Value = serde_json::from_str(&contents)
+            .map_err(|e| AdapterError::State(format!("Failed to parse state: {}", e)))?;
 
-        Ok(state)
+        let changes = migrate(&mut value, MIGRATIONS, STATE_VERSION)?;
+        for change in &changes {
+            tracing::info!("Migrated state {}: {}", path.display(), change);
+        }
+
+        serde_json::from_value(value)
+            .map_err(|e| AdapterError::State(format!("Failed to parse state: {}", e)))
     }
 
     /// Save state to file
+    ///
+    /// The file holds the Homarr API key, so it is only readable by the owner.
+    /// It is written to a temporary file, synced and renamed over the old one,
+    /// so a power loss leaves either the old or the new state. The previous
+    /// state is kept as `<file>.bak`.
     pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
         let path = path.as_ref();
 
@@ -84,38 +469,214 @@ impl State {
             fs::create_dir_all(parent)?;
         }
 
+        // Copy rather than move the current file, so there is a state file at
+        // every point. Don't let a corrupt one replace a good backup.
+        if Self::read(path).is_ok() {
+            write_private(&sibling(path, "bak"), &fs::read(path)?)?;
+        }
+
         let contents = serde_json::to_string_pretty(self)?;
-        fs::write(path, contents)?;
+        let tmp = write_temp(path, contents.as_bytes())?;
+        commit(&tmp, path)
+    }
+
+    /// Check if an API key is stored, inline or by reference
+    pub fn api_key_configured(&self) -> bool {
+        self.api_key.is_some() || self.api_key_ref.is_some()
+    }
+
+    /// Replace app URLs left as keys by the 1.2 migration with registry app IDs
+    ///
+    /// `ids_by_url` maps each registry app's URL to its ID. URLs of apps no
+    /// longer in the registry stay as they are; those apps are pruned by the
+    /// sync like any other. Returns the number of keys replaced.
+    pub fn adopt_app_ids(&mut self, ids_by_url: &HashMap<&str, &str>) -> usize {
+        let mut adopted = 0;
+        let mut adopt = |key: &String| -> String {
+            match ids_by_url.get(key.as_str()) {
+                Some(id) if is_legacy_key(key) => {
+                    adopted += 1;
+                    id.to_string()
+                }
+                _ => key.clone(),
+            }
+        };
+
+        self.discovered_apps = std::mem::take(&mut self.discovered_apps)
+            .into_iter()
+            .map(|(key, app)| (adopt(&key), app))
+            .collect();
+        self.pruned_apps = std::mem::take(&mut self.pruned_apps)
+            .into_iter()
+            .map(|(key, app)| (adopt(&key), app))
+            .collect();
+        for ids in self
+            .removed_apps_by_board
+            .values_mut()
+            .chain(self.board_apps_seen.values_mut())
+        {
+            *ids = ids.iter().map(&mut adopt).collect();
+        }
 
-        Ok(())
+        if adopted > 0 {
+            tracing::info!("Replaced {} app URL key(s) in state with app IDs", adopted);
+        }
+        adopted
+    }
+
+    /// Homarr app ID recorded for a tracked app
+    pub fn app_id(&self, id: &str) -> Option<&str> {
+        self.discovered_apps
+            .get(id)
+            .and_then(|app| app.app_id.as_deref())
+    }
+
+    /// Record the Homarr app ID of a tracked app
+    pub fn record_app_id(&mut self, id: &str, app_id: &str) {
+        if let Some(app) = self.discovered_apps.get_mut(id) {
+            app.app_id = Some(app_id.to_string());
+        }
+    }
+
+    /// Record the Homarr app ID of a tracked app the adapter just created
+    pub fn record_created_app(&mut self, id: &str, app_id: &str) {
+        if let Some(app) = self.discovered_apps.get_mut(id) {
+            app.app_id = Some(app_id.to_string());
+            app.created_by_adapter = true;
+        }
+    }
+
+    /// Board item ID recorded for a tracked app's tile on a board
+    pub fn board_item_id(&self, board_id: &str, id: &str) -> Option<&str> {
+        self.discovered_apps
+            .get(id)
+            .and_then(|app| app.board_items.get(board_id))
+            .map(String::as_str)
+    }
+
+    /// Record which tile each tracked app has on a board
+    ///
+    /// `items` holds the board's app items as (item ID, app ID) pairs. Apps
+    /// without a tile on the board lose their recorded item ID for it.
+    pub fn record_board_items(&mut self, board_id: &str, items: &[(&str, &str)]) {
+        for app in self.discovered_apps.values_mut() {
+            let item_id = app.app_id.as_deref().and_then(|app_id| {
+                items
+                    .iter()
+                    .find(|(_, item_app_id)| *item_app_id == app_id)
+                    .map(|(item_id, _)| item_id.to_string())
+            });
+            match item_id {
+                Some(item_id) => {
+                    app.board_items.insert(board_id.to_string(), item_id);
+                }
+                None => {
+                    app.board_items.remove(board_id);
+                }
+            }
+        }
+    }
+
+    /// Record the tile a tracked app has on a board
+    pub fn record_board_item(&mut self, board_id: &str, id: &str, item_id: &str) {
+        if let Some(app) = self.discovered_apps.get_mut(id) {
+            app.board_items
+                .insert(board_id.to_string(), item_id.to_string());
+        }
     }
 
     /// Check if an app was removed from a specific board
-    pub fn is_removed_from_board(&self, board_id: &str, app_url: &str) -> bool {
+    pub fn is_removed_from_board(&self, board_id: &str, id: &str) -> bool {
         self.removed_apps_by_board
             .get(board_id)
-            .map(|apps| apps.contains(app_url))
+            .map(|apps| apps.contains(id))
             .unwrap_or(false)
     }
 
     /// Mark an app as removed from a specific board
-    #[allow(dead_code)]
-    pub fn mark_removed_from_board(&mut self, board_id: &str, app_url: &str) {
+    pub fn mark_removed_from_board(&mut self, board_id: &str, id: &str) {
         self.removed_apps_by_board
             .entry(board_id.to_string())
             .or_default()
-            .insert(app_url.to_string());
+            .insert(id.to_string());
     }
 
     /// Clear the removed flag for an app on a specific board
     /// Called when user manually re-adds an app to a board
-    #[allow(dead_code)]
-    pub fn clear_removed_from_board(&mut self, board_id: &str, app_url: &str) {
+    pub fn clear_removed_from_board(&mut self, board_id: &str, id: &str) {
         if let Some(apps) = self.removed_apps_by_board.get_mut(board_id) {
-            apps.remove(app_url);
+            apps.remove(id);
         }
     }
 
+    /// Compare a board's current managed apps with the previous sync
+    ///
+    /// An app seen on the board last sync that is now missing (and still active,
+    /// i.e. not being pruned) was removed by the user and is marked as removed.
+    /// A removed app that is present again was re-added by the user and its
+    /// removed flag is cleared. Boards without a previous record are only
+    /// checked for re-adds.
+    ///
+    /// Returns the app IDs newly marked as removed and the IDs cleared.
+    pub fn detect_board_changes(
+        &mut self,
+        board_id: &str,
+        present: &HashSet<String>,
+        active: &HashSet<&str>,
+    ) -> (Vec<String>, Vec<String>) {
+        let mut removed: Vec<String> = self
+            .board_apps_seen
+            .get(board_id)
+            .map(|seen| {
+                seen.iter()
+                    .filter(|id| !present.contains(*id) && active.contains(id.as_str()))
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default();
+        removed.sort();
+
+        let mut readded: Vec<String> = present
+            .iter()
+            .filter(|id| self.is_removed_from_board(board_id, id))
+            .cloned()
+            .collect();
+        readded.sort();
+
+        for id in &removed {
+            self.mark_removed_from_board(board_id, id);
+        }
+        for id in &readded {
+            self.clear_removed_from_board(board_id, id);
+        }
+
+        (removed, readded)
+    }
+
+    /// Forget a pruned app and keep a record of the pruning
+    ///
+    /// Drops the app from `discovered_apps` and from all per-board removal sets.
+    pub fn record_pruned(&mut self, id: &str) {
+        let Some(app) = self.discovered_apps.remove(id) else {
+            return;
+        };
+
+        for apps in self.removed_apps_by_board.values_mut() {
+            apps.remove(id);
+        }
+        self.removed_apps_by_board
+            .retain(|_, apps| !apps.is_empty());
+
+        self.pruned_apps.insert(
+            id.to_string(),
+            PrunedApp {
+                name: app.name,
+                url: app.url,
+                pruned_at: Utc::now(),
+            },
+        );
+    }
+
     /// Update last sync time
     pub fn update_sync_time(&mut self) {
         self.last_sync = Some(Utc::now());
@@ -134,8 +695,7 @@ mod tests {
         assert!(state.removed_apps_by_board.is_empty());
         assert!(state.last_sync.is_none());
         assert!(state.discovered_apps.is_empty());
-        // Default derive uses String::default() (empty), default_version is for serde
-        assert!(state.version.is_empty());
+        assert_eq!(state.version, STATE_VERSION);
     }
 
     #[test]
@@ -174,6 +734,285 @@ mod tests {
         assert!(loaded.last_sync.is_some());
     }
 
+    #[test]
+    fn test_save_is_owner_only_and_keeps_backup() {
+        let temp_dir = TempDir::new().unwrap();
+        let state_path = temp_dir.path().join("state.json");
+        fs::write(&state_path, "{\"first_boot_completed\": true}").unwrap();
+        fs::set_permissions(&state_path, fs::Permissions::from_mode(0o644)).unwrap();
+
+        let state = State {
+            api_key: Some("id.secret".to_string()),
+            ..Default::default()
+        };
+        state.save(&state_path).unwrap();
+
+        let mode = fs::metadata(&state_path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        let backup = temp_dir.path().join("state.json.bak");
+        assert_eq!(
+            fs::metadata(&backup).unwrap().permissions().mode() & 0o777,
+            0o600
+        );
+        assert!(State::read(&backup).unwrap().first_boot_completed);
+        assert!(!temp_dir.path().join("state.json.tmp").exists());
+    }
+
+    #[test]
+    fn test_load_truncated_state_uses_backup() {
+        let temp_dir = TempDir::new().unwrap();
+        let state_path = temp_dir.path().join("state.json");
+
+        let mut state = State::default();
+        state.mark_removed_from_board("board-1", "http://app1.local");
+        state.save(&state_path).unwrap();
+        state.first_boot_completed = true;
+        state.save(&state_path).unwrap();
+
+        // Simulate a write cut short by a power loss
+        fs::write(&state_path, "{\"first_boot_completed\": tr").unwrap();
+
+        let loaded = State::load(&state_path).unwrap();
+        assert!(loaded.is_removed_from_board("board-1", "http://app1.local"));
+        assert!(!loaded.first_boot_completed);
+        assert!(temp_dir.path().join("state.json.corrupt").exists());
+    }
+
+    #[test]
+    fn test_load_missing_state_uses_backup() {
+        let temp_dir = TempDir::new().unwrap();
+        let state_path = temp_dir.path().join("state.json");
+
+        let state = State {
+            first_boot_completed: true,
+            ..Default::default()
+        };
+        state.save(&state_path).unwrap();
+        state.save(&state_path).unwrap();
+        // Simulate losing the state file, leaving only the backup
+        fs::remove_file(&state_path).unwrap();
+
+        let loaded = State::load(&state_path).unwrap();
+        assert!(loaded.first_boot_completed);
+    }
+
+    #[test]
+    fn test_load_readonly_leaves_corrupt_state_in_place() {
+        let temp_dir = TempDir::new().unwrap();
+        let state_path = temp_dir.path().join("state.json");
+
+        let state = State {
+            first_boot_completed: true,
+            ..Default::default()
+        };
+        state.save(&state_path).unwrap();
+        state.save(&state_path).unwrap();
+        fs::write(&state_path, "{\"first_boot_completed\": tr").unwrap();
+
+        let loaded = State::load_readonly(&state_path).unwrap();
+        assert!(loaded.first_boot_completed);
+        assert_eq!(
+            fs::read_to_string(&state_path).unwrap(),
+            "{\"first_boot_completed\": tr"
+        );
+        assert!(!temp_dir.path().join("state.json.corrupt").exists());
+
+        let missing = State::load_readonly(temp_dir.path().join("missing.json")).unwrap();
+        assert!(!missing.first_boot_completed);
+    }
+
+    #[test]
+    fn test_load_unreadable_state_is_an_error_and_kept() {
+        let temp_dir = TempDir::new().unwrap();
+        let state_path = temp_dir.path().join("state.json");
+        State::default().save(&state_path).unwrap();
+        State::default().save(&state_path).unwrap();
+        // Reading a directory fails with an I/O error, like a permission problem
+        // would (file modes don't stop root, which the tests may run as)
+        fs::remove_file(&state_path).unwrap();
+        fs::create_dir(&state_path).unwrap();
+
+        let result = State::load(&state_path);
+
+        assert!(matches!(result, Err(AdapterError::Io(_))));
+        assert!(state_path.is_dir());
+        assert!(!temp_dir.path().join("state.json.corrupt").exists());
+    }
+
+    #[test]
+    fn test_load_corrupt_state_without_backup_uses_defaults() {
+        let temp_dir = TempDir::new().unwrap();
+        let state_path = temp_dir.path().join("state.json");
+        fs::write(&state_path, "").unwrap();
+
+        let loaded = State::load(&state_path).unwrap();
+        assert!(!loaded.first_boot_completed);
+
+        // The next save must not turn the corrupt file into the backup
+        loaded.save(&state_path).unwrap();
+        assert!(!temp_dir.path().join("state.json.bak").exists());
+    }
+
+    fn rename_removed(value: &mut serde_json::Value) -> Vec<String> {
+        match value.as_object_mut().unwrap().remove("removed") {
+            Some(removed) => {
+                value["removed_apps_by_board"] = removed;
+                vec!["renamed removed to removed_apps_by_board".to_string()]
+            }
+            None => vec![],
+        }
+    }
+
+    fn add_marker(value: &mut serde_json::Value) -> Vec<String> {
+        value["marker"] = serde_json::json!(true);
+        vec!["added marker".to_string()]
+    }
+
+    const TEST_MIGRATIONS: &[Migration] = &[
+        Migration {
+            from: "1.0",
+            to: "1.1",
+            apply: rename_removed,
+        },
+        Migration {
+            from: "1.1",
+            to: "2.0",
+            apply: add_marker,
+        },
+    ];
+
+    #[test]
+    fn test_migrate_step_by_step() {
+        let mut value = serde_json::json!({
+            "removed": {"board-1": ["http://a"]}
+        });
+
+        let changes = migrate(&mut value, TEST_MIGRATIONS, "2.0").unwrap();
+
+        assert_eq!(
+            changes,
+            vec![
+                "1.0 -> 1.1: renamed removed to removed_apps_by_board",
+                "1.1 -> 2.0: added marker"
+            ]
+        );
+        assert_eq!(value["version"], "2.0");
+        assert_eq!(value["removed_apps_by_board"]["board-1"][0], "http://a");
+        assert_eq!(value["marker"], true);
+    }
+
+    #[test]
+    fn test_migrate_starts_from_stored_version() {
+        let mut value = serde_json::json!({"version": "1.1"});
+
+        let changes = migrate(&mut value, TEST_MIGRATIONS, "2.0").unwrap();
+
+        assert_eq!(changes, vec!["1.1 -> 2.0: added marker"]);
+    }
+
+    #[test]
+    fn test_migrate_current_version_is_unchanged() {
+        let mut value = serde_json::json!({"version": "2.0", "removed": {}});
+
+        let changes = migrate(&mut value, TEST_MIGRATIONS, "2.0").unwrap();
+
+        assert!(changes.is_empty());
+        assert!(value.get("removed").is_some());
+    }
+
+    #[test]
+    fn test_migrate_refuses_downgrade() {
+        let mut value = serde_json::json!({"version": "2.1"});
+
+        let result = migrate(&mut value, TEST_MIGRATIONS, "2.0");
+
+        assert!(matches!(result, Err(AdapterError::StateVersion { .. })));
+    }
+
+    #[test]
+    fn test_load_newer_state_is_refused_and_kept() {
+        let temp_dir = TempDir::new().unwrap();
+        let state_path = temp_dir.path().join("state.json");
+        fs::write(&state_path, "{\"version\": \"99.0\"}").unwrap();
+
+        let result = State::load(&state_path);
+
+        assert!(matches!(result, Err(AdapterError::StateVersion { .. })));
+        assert!(state_path.exists());
+    }
+
+    #[test]
+    fn test_record_app_and_board_item_ids() {
+        let mut state = State::default();
+        state.discovered_apps.insert(
+            "http://a".to_string(),
+            DiscoveredApp {
+                name: "A".to_string(),
+                ..Default::default()
+            },
+        );
+        state.record_app_id("http://a", "app-a");
+        state.record_app_id("http://unknown", "app-x");
+
+        state.record_board_items("board-1", &[("user-item", "app-b"), ("item-a", "app-a")]);
+        state.record_board_items("board-2", &[("item-a2", "app-a")]);
+
+        assert_eq!(state.app_id("http://a"), Some("app-a"));
+        assert_eq!(state.app_id("http://unknown"), None);
+        assert_eq!(state.board_item_id("board-1", "http://a"), Some("item-a"));
+        assert_eq!(state.board_item_id("board-2", "http://a"), Some("item-a2"));
+
+        // The tile is gone from board-2
+        state.record_board_items("board-2", &[]);
+        assert_eq!(state.board_item_id("board-2", "http://a"), None);
+        assert_eq!(state.board_item_id("board-1", "http://a"), Some("item-a"));
+    }
+
+    #[test]
+    fn test_load_unversioned_state() {
+        let temp_dir = TempDir::new().unwrap();
+        let state_path = temp_dir.path().join("state.json");
+        fs::write(&state_path, "{\"first_boot_completed\": true}").unwrap();
+
+        let state = State::load(&state_path).unwrap();
+
+        assert_eq!(state.version, STATE_VERSION);
+        assert!(state.first_boot_completed);
+    }
+
+    #[test]
+    fn test_load_state_with_empty_version() {
+        let temp_dir = TempDir::new().unwrap();
+        let state_path = temp_dir.path().join("state.json");
+        fs::write(
+            &state_path,
+            r#"{
+                "version": "",
+                "first_boot_completed": true,
+                "authelia_sync_completed": true,
+                "api_key": "key-id.secret",
+                "removed_apps_by_board": {"board-1": ["http://a"]},
+                "last_sync": null,
+                "discovered_apps": {
+                    "http://a": {
+                        "name": "A",
+                        "container_id": "",
+                        "added_at": "2025-01-01T00:00:00Z"
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let state = State::load(&state_path).unwrap();
+
+        assert_eq!(state.version, STATE_VERSION);
+        assert_eq!(state.api_key.as_deref(), Some("key-id.secret"));
+        assert!(state.is_removed_from_board("board-1", "http://a"));
+        assert!(state.discovered_apps.contains_key("http://a"));
+        assert!(!sibling(&state_path, "corrupt").exists());
+    }
+
     #[test]
     fn test_per_board_removal_tracking() {
         let mut state = State::default();
@@ -230,76 +1069,234 @@ mod tests {
         assert!(nested_path.exists());
     }
 
-    // Tests for URL-based deduplication (issue #15)
+    // Tests for ID-based deduplication (issue #15)
 
     #[test]
-    fn test_discovered_apps_keyed_by_url() {
+    fn test_discovered_apps_keyed_by_id() {
         let mut state = State::default();
-        let url = "http://localhost:3000".to_string();
 
         state.discovered_apps.insert(
-            url.clone(),
+            "signalk".to_string(),
             DiscoveredApp {
                 name: "Signal K".to_string(),
+                url: "http://localhost:3000".to_string(),
                 container_id: "abc123".to_string(),
                 added_at: Utc::now(),
+                ..Default::default()
             },
         );
 
-        // Should find by URL
-        assert!(state.discovered_apps.contains_key(&url));
-        // Should NOT find by container_id (that's not the key anymore)
+        // Should find by app ID
+        assert!(state.discovered_apps.contains_key("signalk"));
+        // Should NOT find by URL or container_id (those are not the key anymore)
+        assert!(!state.discovered_apps.contains_key("http://localhost:3000"));
         assert!(!state.discovered_apps.contains_key("abc123"));
     }
 
     #[test]
-    fn test_same_url_different_container_id_no_duplicate() {
+    fn test_same_id_different_container_id_no_duplicate() {
         let mut state = State::default();
-        let url = "http://localhost:3000".to_string();
 
-        // First container with this URL
+        // First container for this app
         state.discovered_apps.insert(
-            url.clone(),
+            "signalk".to_string(),
             DiscoveredApp {
                 name: "Signal K".to_string(),
                 container_id: "abc123".to_string(),
                 added_at: Utc::now(),
+                ..Default::default()
             },
         );
 
-        // Same URL, different container (after restart)
+        // Same app, different container (after restart)
         // HashMap.insert with same key replaces the value - no duplicate possible
-        // In the actual app code, we use get_mut() to update container_id in place
+        // In the actual app code, the entry is updated in place
         state.discovered_apps.insert(
-            url.clone(),
+            "signalk".to_string(),
             DiscoveredApp {
                 name: "Signal K".to_string(),
                 container_id: "def456".to_string(),
                 added_at: Utc::now(),
+                ..Default::default()
             },
         );
 
-        // Should have exactly one entry (URL-keyed HashMap prevents duplicates)
+        // Should have exactly one entry (ID-keyed HashMap prevents duplicates)
         assert_eq!(state.discovered_apps.len(), 1);
         // Should have the new container_id
         assert_eq!(
-            state.discovered_apps.get(&url).unwrap().container_id,
+            state.discovered_apps.get("signalk").unwrap().container_id,
             "def456"
         );
     }
 
     #[test]
-    fn test_removed_apps_tracked_by_url_per_board() {
+    fn test_removed_apps_tracked_by_id_per_board() {
         let mut state = State::default();
         let board_id = "test-board";
+
+        assert!(!state.is_removed_from_board(board_id, "signalk"));
+        state.mark_removed_from_board(board_id, "signalk");
+        assert!(state.is_removed_from_board(board_id, "signalk"));
+
+        // A new URL or container for the same app ID is still considered removed
+        // (we track by ID, not URL or container_id)
+    }
+
+    #[test]
+    fn test_migrate_1_1_records_urls() {
+        let mut value = serde_json::json!({
+            "version": "1.1",
+            "discovered_apps": {
+                "http://localhost:3000": {
+                    "name": "Signal K",
+                    "container_id": "",
+                    "added_at": "2025-01-15T10:30:00Z"
+                }
+            },
+            "removed_apps_by_board": {"board-1": ["http://localhost:3000"]}
+        });
+
+        let changes = migrate(&mut value, MIGRATIONS, STATE_VERSION).unwrap();
+
+        assert_eq!(changes.len(), 1);
+        let state: State = serde_json::from_value(value).unwrap();
+        assert_eq!(
+            state.discovered_apps["http://localhost:3000"].url,
+            "http://localhost:3000"
+        );
+    }
+
+    #[test]
+    fn test_adopt_app_ids() {
+        let mut state = State::default();
+        for (key, name) in [
+            ("http://localhost:3000", "Signal K"),
+            ("http://gone", "Gone"),
+        ] {
+            state.discovered_apps.insert(
+                key.to_string(),
+                DiscoveredApp {
+                    name: name.to_string(),
+                    url: key.to_string(),
+                    ..Default::default()
+                },
+            );
+        }
+        state.mark_removed_from_board("board-1", "http://localhost:3000");
+        state
+            .board_apps_seen
+            .insert("board-2".to_string(), url_set(&["http://localhost:3000"]));
+        let ids_by_url: HashMap<&str, &str> =
+            [("http://localhost:3000", "signalk")].into_iter().collect();
+
+        assert_eq!(state.adopt_app_ids(&ids_by_url), 3);
+
+        assert_eq!(state.discovered_apps["signalk"].name, "Signal K");
+        // Not in the registry any more; left for pruning
+        assert!(state.discovered_apps.contains_key("http://gone"));
+        assert!(state.is_removed_from_board("board-1", "signalk"));
+        assert!(state.board_apps_seen["board-2"].contains("signalk"));
+
+        // Nothing left to adopt
+        assert_eq!(state.adopt_app_ids(&ids_by_url), 0);
+    }
+
+    #[test]
+    fn test_record_pruned() {
+        let mut state = State::default();
         let url = "http://localhost:3000";
+        state.discovered_apps.insert(
+            url.to_string(),
+            DiscoveredApp {
+                name: "Signal K".to_string(),
+                container_id: "signalk-server".to_string(),
+                added_at: Utc::now(),
+                ..Default::default()
+            },
+        );
+        state.mark_removed_from_board("board-a", url);
+        state.mark_removed_from_board("board-b", "http://other.local");
+
+        state.record_pruned(url);
+
+        assert!(!state.discovered_apps.contains_key(url));
+        assert!(!state.is_removed_from_board("board-a", url));
+        assert!(!state.removed_apps_by_board.contains_key("board-a"));
+        assert!(state.is_removed_from_board("board-b", "http://other.local"));
+        assert_eq!(state.pruned_apps.get(url).unwrap().name, "Signal K");
+    }
+
+    fn url_set(urls: &[&str]) -> HashSet<String> {
+        urls.iter().map(|u| u.to_string()).collect()
+    }
+
+    #[test]
+    fn test_detect_board_changes_user_removed_tile() {
+        let mut state = State::default();
+        state
+            .board_apps_seen
+            .insert("board-a".to_string(), url_set(&["http://a", "http://b"]));
+        let active: HashSet<&str> = ["http://a", "http://b"].into_iter().collect();
+
+        let (removed, readded) =
+            state.detect_board_changes("board-a", &url_set(&["http://a"]), &active);
+
+        assert_eq!(removed, vec!["http://b".to_string()]);
+        assert!(readded.is_empty());
+        assert!(state.is_removed_from_board("board-a", "http://b"));
+        // Other boards are unaffected (FR-2.6)
+        assert!(!state.is_removed_from_board("board-b", "http://b"));
+    }
+
+    #[test]
+    fn test_detect_board_changes_user_readded_tile() {
+        let mut state = State::default();
+        state.mark_removed_from_board("board-a", "http://b");
+        state
+            .board_apps_seen
+            .insert("board-a".to_string(), url_set(&["http://a"]));
+        let active: HashSet<&str> = ["http://a", "http://b"].into_iter().collect();
+
+        let (removed, readded) =
+            state.detect_board_changes("board-a", &url_set(&["http://a", "http://b"]), &active);
+
+        assert!(removed.is_empty());
+        assert_eq!(readded, vec!["http://b".to_string()]);
+        assert!(!state.is_removed_from_board("board-a", "http://b"));
+    }
+
+    #[test]
+    fn test_detect_board_changes_ignores_inactive_apps() {
+        let mut state = State::default();
+        state
+            .board_apps_seen
+            .insert("board-a".to_string(), url_set(&["http://a", "http://gone"]));
+        let active: HashSet<&str> = ["http://a"].into_iter().collect();
 
-        assert!(!state.is_removed_from_board(board_id, url));
-        state.mark_removed_from_board(board_id, url);
-        assert!(state.is_removed_from_board(board_id, url));
+        let (removed, _) = state.detect_board_changes("board-a", &url_set(&["http://a"]), &active);
 
-        // Different container ID with same URL should still be considered removed
-        // (we track by URL, not container_id)
+        // App no longer in the registry is being pruned, not removed by the user
+        assert!(removed.is_empty());
+        assert!(!state.is_removed_from_board("board-a", "http://gone"));
+    }
+
+    #[test]
+    fn test_detect_board_changes_new_board_has_no_removals() {
+        let mut state = State::default();
+        let active: HashSet<&str> = ["http://a"].into_iter().collect();
+
+        let (removed, readded) = state.detect_board_changes("new-board", &HashSet::new(), &active);
+
+        assert!(removed.is_empty());
+        assert!(readded.is_empty());
+    }
+
+    #[test]
+    fn test_record_pruned_unknown_app_is_noop() {
+        let mut state = State::default();
+        state.record_pruned("http://unknown.local");
+        assert!(state.pruned_apps.is_empty());
     }
 
     #[test]
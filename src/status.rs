@@ -0,0 +1,912 @@
+//! Status reporting
+//!
+//! `status` reports the persistent state (without the API key) and, with
+//! `--live`, what Homarr and Docker currently look like. With `--drift` it
+//! compares the registry, the state and Homarr and lists what is out of sync.
+//! The report is rendered as text for people or as JSON/YAML for scripts.
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use crate::apikey;
+use crate::config::Config;
+use crate::docker;
+use crate::error::AdapterError;
+use crate::homarr::{self, HomarrClient};
+use crate::registry::{self, RegistryEntry};
+use crate::retry::RequestPolicy;
+use crate::state::State;
+use crate::sync::{self, SyncInputs};
+
+/// State fields that are never reported
+const SECRET_FIELDS: &[&str] = &["api_key"];
+
+/// Live view of Homarr and Docker
+#[derive(Debug, Default, Serialize)]
+pub struct LiveStatus {
+    pub homarr: HomarrStatus,
+    pub docker: DockerStatus,
+}
+
+/// What Homarr reports right now
+#[derive(Debug, Default, Serialize)]
+pub struct HomarrStatus {
+    /// Whether Homarr answered
+    pub reachable: bool,
+
+    /// Whether Homarr accepted the stored API key (None if unknown)
+    pub api_key_valid: Option<bool>,
+
+    /// Number of apps in Homarr's app registry
+    pub app_count: Option<usize>,
+
+    /// Names of boards the adapter can see
+    pub boards: Vec<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// What Docker reports right now
+#[derive(Debug, Default, Serialize)]
+pub struct DockerStatus {
+    /// Whether the Docker daemon answered
+    pub connected: bool,
+
+    /// State of each container referenced by the registry or state
+    /// ("missing" if it does not exist)
+    pub containers: BTreeMap<String, String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Differences between the registry, the state and Homarr
+#[derive(Debug, Default, Serialize)]
+pub struct DriftReport {
+    /// Registry apps that don't exist in Homarr
+    pub missing_apps: Vec<DriftApp>,
+
+    /// Registry apps without a tile on a board (not removed by the user)
+    pub missing_tiles: Vec<DriftTile>,
+
+    /// Apps the adapter added that are no longer in the registry
+    pub orphaned_apps: Vec<DriftApp>,
+
+    /// Homarr apps whose name, icon or URL differ from the registry
+    pub mismatched_apps: Vec<DriftMismatch>,
+
+    /// Tiles the user removed, recorded in state
+    pub removed_by_user: Vec<DriftTile>,
+
+    /// Problems that make the report incomplete
+    pub errors: Vec<String>,
+}
+
+/// An app in a drift report
+#[derive(Debug, Serialize)]
+pub struct DriftApp {
+    /// Registry app ID
+    pub id: String,
+    pub name: String,
+    pub url: String,
+
+    /// Homarr app ID, if the app exists in Homarr
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub app_id: Option<String>,
+
+    /// Boards with a tile for the app
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub boards: Vec<String>,
+}
+
+/// An app tile on a board in a drift report
+#[derive(Debug, Serialize)]
+pub struct DriftTile {
+    pub board: String,
+    /// Registry app ID
+    pub id: String,
+    pub name: String,
+    pub url: String,
+}
+
+/// A field that differs between the registry and Homarr
+#[derive(Debug, Serialize)]
+pub struct DriftMismatch {
+    /// Registry app ID
+    pub id: String,
+    pub name: String,
+    pub app_id: String,
+    pub field: String,
+    pub registry: String,
+    pub homarr: String,
+}
+
+impl DriftReport {
+    /// Check if nothing is out of sync
+    pub fn is_clean(&self) -> bool {
+        self.missing_apps.is_empty()
+            && self.missing_tiles.is_empty()
+            && self.orphaned_apps.is_empty()
+            && self.mismatched_apps.is_empty()
+            && self.removed_by_user.is_empty()
+    }
+}
+
+/// Fetch the registry and Homarr's apps and boards, and compare them with the state
+///
+/// Uses the API key stored in state and never changes anything in Homarr.
+/// State still keyed by app URL is re-keyed in memory, as the next sync would.
+pub async fn drift_status(config: &Config, state: &mut State) -> DriftReport {
+    let api_key = match apikey::stored_key(state) {
+        Ok(Some(api_key)) => api_key,
+        Ok(None) => {
+            return DriftReport {
+                errors: vec!["No API key stored".to_string()],
+                ..Default::default()
+            }
+        }
+        Err(e) => {
+            return DriftReport {
+                errors: vec![e.to_string()],
+                ..Default::default()
+            }
+        }
+    };
+    let mut client = match HomarrClient::new(&config.homarr_url, RequestPolicy::from_config(config))
+    {
+        Ok(client) => client,
+        Err(e) => {
+            return DriftReport {
+                errors: vec![e.to_string()],
+                ..Default::default()
+            }
+        }
+    };
+    client.set_api_key(api_key);
+
+    match sync::gather_inputs(&client, config).await {
+        Some(inputs) => {
+            let ids_by_url = inputs
+                .entries
+                .iter()
+                .map(|e| (e.app.url.as_str(), e.app.id.as_str()))
+                .collect();
+            state.adopt_app_ids(&ids_by_url);
+            drift_report(&inputs, state)
+        }
+        None => DriftReport {
+            errors: vec!["No writable boards found in Homarr".to_string()],
+            ..Default::default()
+        },
+    }
+}
+
+/// Compare registry entries, state and Homarr contents
+pub fn drift_report(inputs: &SyncInputs, state: &State) -> DriftReport {
+    let mut report = DriftReport::default();
+
+    if !inputs.can_prune {
+        report
+            .errors
+            .push("Some sources could not be read; the report may be incomplete".to_string());
+    }
+
+    let visible: Vec<&RegistryEntry> = inputs
+        .entries
+        .iter()
+        .filter(|e| e.app.is_visible())
+        .collect();
+    let boards_with = |app_id: &str| -> Vec<String> {
+        inputs
+            .boards
+            .iter()
+            .filter(|b| homarr::board_has_app(&b.items, app_id))
+            .map(|b| b.name.clone())
+            .collect()
+    };
+
+    for entry in &visible {
+        let app = &entry.app;
+
+        let Some(existing) = sync::find_app(state, &inputs.existing_apps, &app.id, &app.url) else {
+            // An app with the same name but another URL was probably edited in Homarr
+            let renamed = inputs.existing_apps.iter().find(|a| {
+                a.name == app.name
+                    && !visible
+                        .iter()
+                        .any(|e| a.href.as_deref() == Some(e.app.url.as_str()))
+            });
+            match renamed {
+                Some(existing) => report.mismatched_apps.push(DriftMismatch {
+                    id: app.id.clone(),
+                    name: app.name.clone(),
+                    app_id: existing.id.clone(),
+                    field: "url".to_string(),
+                    registry: app.url.clone(),
+                    homarr: existing.href.clone().unwrap_or_default(),
+                }),
+                None if inputs.apps_fetched => report.missing_apps.push(DriftApp {
+                    id: app.id.clone(),
+                    name: app.name.clone(),
+                    url: app.url.clone(),
+                    app_id: None,
+                    boards: vec![],
+                }),
+                None => {}
+            }
+            continue;
+        };
+
+        let expected_icon = homarr::app_icon_url(app);
+        for (field, registry_value, homarr_value) in [
+            ("name", app.name.as_str(), existing.name.as_str()),
+            ("icon", expected_icon.as_str(), existing.icon_url.as_str()),
+            (
+                "url",
+                app.url.as_str(),
+                existing.href.as_deref().unwrap_or(""),
+            ),
+        ] {
+            if registry_value != homarr_value {
+                report.mismatched_apps.push(DriftMismatch {
+                    id: app.id.clone(),
+                    name: app.name.clone(),
+                    app_id: existing.id.clone(),
+                    field: field.to_string(),
+                    registry: registry_value.to_string(),
+                    homarr: homarr_value.to_string(),
+                });
+            }
+        }
+
+        for board in &inputs.boards {
+            if !homarr::board_has_app(&board.items, &existing.id)
+                && !state.is_removed_from_board(&board.id, &app.id)
+            {
+                report.missing_tiles.push(DriftTile {
+                    board: board.name.clone(),
+                    id: app.id.clone(),
+                    name: app.name.clone(),
+                    url: app.url.clone(),
+                });
+            }
+        }
+    }
+
+    let mut tracked: Vec<_> = state.discovered_apps.iter().collect();
+    tracked.sort_by_key(|(id, _)| id.as_str());
+    for (id, tracked_app) in tracked {
+        if visible.iter().any(|e| &e.app.id == id) {
+            continue;
+        }
+        let app_id = sync::find_app(state, &inputs.existing_apps, id, &tracked_app.url)
+            .map(|a| a.id.clone());
+        report.orphaned_apps.push(DriftApp {
+            id: id.clone(),
+            name: tracked_app.name.clone(),
+            url: tracked_app.url.clone(),
+            boards: app_id.as_deref().map(boards_with).unwrap_or_default(),
+            app_id,
+        });
+    }
+
+    for (board_id, ids) in &state.removed_apps_by_board {
+        let board = inputs
+            .boards
+            .iter()
+            .find(|b| &b.id == board_id)
+            .map_or(board_id.as_str(), |b| b.name.as_str());
+        for id in ids {
+            let (name, url) = visible
+                .iter()
+                .find(|e| &e.app.id == id)
+                .map(|e| (e.app.name.clone(), e.app.url.clone()))
+                .or_else(|| {
+                    state
+                        .discovered_apps
+                        .get(id)
+                        .map(|a| (a.name.clone(), a.url.clone()))
+                })
+                .unwrap_or_else(|| (id.clone(), String::new()));
+            report.removed_by_user.push(DriftTile {
+                board: board.to_string(),
+                id: id.clone(),
+                name,
+                url,
+            });
+        }
+    }
+    report
+        .removed_by_user
+        .sort_by(|a, b| (&a.board, &a.id).cmp(&(&b.board, &b.id)));
+
+    report
+}
+
+/// Render a drift report as human-readable text
+pub fn render_drift_text(report: &DriftReport) -> String {
+    let mut out = String::new();
+
+    let _ = writeln!(out, "Drift:");
+    if report.is_clean() {
+        let _ = writeln!(out, "  Registry, state and Homarr are in sync");
+    }
+    for app in &report.missing_apps {
+        let _ = writeln!(out, "  - {} ({}) missing in Homarr", app.name, app.url);
+    }
+    for tile in &report.missing_tiles {
+        let _ = writeln!(
+            out,
+            "  - {} ({}) missing on board '{}'",
+            tile.name, tile.url, tile.board
+        );
+    }
+    for app in &report.orphaned_apps {
+        let boards = if app.boards.is_empty() {
+            String::new()
+        } else {
+            format!(", on {}", app.boards.join(", "))
+        };
+        let _ = writeln!(
+            out,
+            "  - {} ({}) no longer in the registry{}",
+            app.name, app.url, boards
+        );
+    }
+    for mismatch in &report.mismatched_apps {
+        let _ = writeln!(
+            out,
+            "  ~ {}: {} is '{}' in Homarr, '{}' in the registry",
+            mismatch.name, mismatch.field, mismatch.homarr, mismatch.registry
+        );
+    }
+    for tile in &report.removed_by_user {
+        let _ = writeln!(
+            out,
+            "  = {} ({}) removed from board '{}' by user",
+            tile.name, tile.url, tile.board
+        );
+    }
+    for error in &report.errors {
+        let _ = writeln!(out, "  ! {}", error);
+    }
+
+    out
+}
+
+/// Build the status report for a state
+///
+/// Contains every state field except secrets, with `api_key_configured`
+/// in place of the API key. Maps and sets are sorted so output is stable.
+pub fn state_report(state: &State) -> serde_json::Value {
+    let mut report = serde_json::to_value(state).unwrap_or_default();
+    if let Some(fields) = report.as_object_mut() {
+        for field in SECRET_FIELDS {
+            fields.remove(*field);
+        }
+        fields.insert(
+            "api_key_configured".to_string(),
+            state.api_key_configured().into(),
+        );
+    }
+    sort_sets(&mut report);
+    report
+}
+
+/// Sort arrays of strings (serialized sets) anywhere in a value
+fn sort_sets(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Array(items) => {
+            if items.iter().all(|v| v.is_string()) {
+                items.sort_by(|a, b| a.as_str().cmp(&b.as_str()));
+            } else {
+                items.iter_mut().for_each(sort_sets);
+            }
+        }
+        serde_json::Value::Object(fields) => fields.values_mut().for_each(sort_sets),
+        _ => {}
+    }
+}
+
+/// Query Homarr and Docker
+///
+/// Uses the API key stored in state and never changes anything. Failures
+/// are reported in the result instead of aborting.
+pub async fn live_status(config: &Config, state: &State) -> LiveStatus {
+    LiveStatus {
+        homarr: homarr_status(config, state).await,
+        docker: docker_status(config, state).await,
+    }
+}
+
+async fn homarr_status(config: &Config, state: &State) -> HomarrStatus {
+    let mut status = HomarrStatus::default();
+
+    let api_key = match apikey::stored_key(state) {
+        Ok(Some(api_key)) => api_key,
+        Ok(None) => {
+            status.error = Some("No API key stored".to_string());
+            return status;
+        }
+        Err(e) => {
+            status.error = Some(e.to_string());
+            return status;
+        }
+    };
+    let mut client = match HomarrClient::new(&config.homarr_url, RequestPolicy::from_config(config))
+    {
+        Ok(client) => client,
+        Err(e) => {
+            status.error = Some(e.to_string());
+            return status;
+        }
+    };
+    client.set_api_key(api_key);
+
+    match client.check_api_key().await {
+        Ok(valid) => {
+            status.reachable = true;
+            status.api_key_valid = Some(valid);
+        }
+        Err(e) => {
+            status.reachable = !matches!(e, AdapterError::Http(_));
+            status.error = Some(e.to_string());
+            return status;
+        }
+    }
+
+    match client.get_all_apps().await {
+        Ok(apps) => status.app_count = Some(apps.len()),
+        Err(e) => status.error = Some(e.to_string()),
+    }
+    match client.get_all_boards().await {
+        Ok(boards) => status.boards = boards.into_iter().map(|b| b.name).collect(),
+        Err(e) => status.error = Some(e.to_string()),
+    }
+
+    status
+}
+
+async fn docker_status(config: &Config, state: &State) -> DockerStatus {
+    let mut status = DockerStatus::default();
+
+    // Containers the dashboard depends on
+    let mut names: Vec<String> = state
+        .discovered_apps
+        .values()
+        .map(|app| app.container_id.clone())
+        .filter(|name| !name.is_empty())
+        .collect();
+    if let Ok(entries) = registry::load_all_apps(&config.registry_dir) {
+        names.extend(
+            entries
+                .iter()
+                .filter_map(|e| e.app.container_name().map(str::to_string)),
+        );
+    }
+
+    let states = match docker::connect(&config.docker_socket) {
+        Ok(docker) => docker::container_states(&docker).await,
+        Err(e) => Err(e),
+    };
+    match states {
+        Ok(states) => {
+            status.connected = true;
+            for name in names {
+                let state = states
+                    .get(&name)
+                    .cloned()
+                    .unwrap_or_else(|| "missing".to_string());
+                status.containers.insert(name, state);
+            }
+        }
+        Err(e) => status.error = Some(e.to_string()),
+    }
+
+    status
+}
+
+/// Render the status as human-readable text
+pub fn render_text(state: &State, live: Option<&LiveStatus>) -> String {
+    let mut out = String::new();
+
+    if !state.first_boot_completed {
+        let _ = writeln!(out, "Status: First-boot setup pending");
+        return out;
+    }
+
+    let _ = writeln!(out, "Status: First-boot setup completed");
+    let _ = writeln!(
+        out,
+        "Authelia sync: {}",
+        if state.authelia_sync_completed {
+            "completed"
+        } else {
+            "not completed"
+        }
+    );
+    let _ = writeln!(
+        out,
+        "API key: {}",
+        match (state.auth_failure, state.api_key_configured()) {
+            (Some(failure), _) => format!("error: {}", failure.describe()),
+            (None, true) => {
+                let mut text = "configured".to_string();
+                match apikey::KeyStore::from_state(state) {
+                    Ok(apikey::KeyStore::State) => {}
+                    Ok(store) => text.push_str(&format!(" in {}", store)),
+                    Err(e) => text.push_str(&format!(" ({})", e)),
+                }
+                if let Some(created) = state.api_key_created_at {
+                    text.push_str(&format!(" (created {})", created.to_rfc3339()));
+                }
+                text
+            }
+            (None, false) => "not configured".to_string(),
+        }
+    );
+    if state.api_key_configured() {
+        let _ = writeln!(
+            out,
+            "Bootstrap key: {}",
+            match state.bootstrap_key_revoked_at {
+                Some(at) => format!("revoked (verified {})", at.to_rfc3339()),
+                None => "not verified as revoked".to_string(),
+            }
+        );
+    }
+    let _ = writeln!(
+        out,
+        "Last sync: {}",
+        state
+            .last_sync
+            .map(|t| t.to_rfc3339())
+            .unwrap_or_else(|| "never".to_string())
+    );
+
+    let apps: BTreeMap<_, _> = state.discovered_apps.iter().collect();
+    let _ = writeln!(out, "Registered apps: {}", apps.len());
+    for (id, app) in apps {
+        let container_info = if app.container_id.is_empty() {
+            "external".to_string()
+        } else {
+            format!("container: {}", app.container_id)
+        };
+        let _ = writeln!(
+            out,
+            "  - {} ({}) [id: {}, {}]",
+            app.name, app.url, id, container_info
+        );
+    }
+
+    let removed: BTreeMap<_, _> = state
+        .removed_apps_by_board
+        .iter()
+        .filter(|(_, urls)| !urls.is_empty())
+        .collect();
+    if !removed.is_empty() {
+        let _ = writeln!(out, "Removed by user:");
+        for (board_id, urls) in removed {
+            let mut urls: Vec<_> = urls.iter().map(String::as_str).collect();
+            urls.sort();
+            let _ = writeln!(out, "  - board {}: {}", board_id, urls.join(", "));
+        }
+    }
+
+    if !state.pruned_apps.is_empty() {
+        let pruned: BTreeMap<_, _> = state.pruned_apps.iter().collect();
+        let _ = writeln!(out, "Pruned apps: {}", pruned.len());
+        for (id, app) in pruned {
+            let _ = writeln!(
+                out,
+                "  - {} ({}) [id: {}] pruned at {}",
+                app.name,
+                app.url,
+                id,
+                app.pruned_at.to_rfc3339()
+            );
+        }
+    }
+
+    if let Some(live) = live {
+        let homarr = &live.homarr;
+        let _ = writeln!(
+            out,
+            "Homarr: {}",
+            if homarr.reachable {
+                "reachable"
+            } else {
+                "unreachable"
+            }
+        );
+        if let Some(valid) = homarr.api_key_valid {
+            let _ = writeln!(
+                out,
+                "  API key: {}",
+                if valid { "valid" } else { "rejected" }
+            );
+        }
+        if let Some(count) = homarr.app_count {
+            let _ = writeln!(out, "  Apps: {}", count);
+        }
+        if !homarr.boards.is_empty() {
+            let _ = writeln!(out, "  Boards: {}", homarr.boards.join(", "));
+        }
+        if let Some(ref error) = homarr.error {
+            let _ = writeln!(out, "  Error: {}", error);
+        }
+
+        let docker = &live.docker;
+        let _ = writeln!(
+            out,
+            "Docker: {}",
+            if docker.connected {
+                "connected"
+            } else {
+                "not connected"
+            }
+        );
+        for (name, state) in &docker.containers {
+            let _ = writeln!(out, "  - {}: {}", name, state);
+        }
+        if let Some(ref error) = docker.error {
+            let _ = writeln!(out, "  Error: {}", error);
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::homarr::{Board, SelectableApp};
+    use crate::registry::{AppDefinition, AppSource, AppType, LayoutConfig};
+    use crate::state::{AuthFailure, DiscoveredApp};
+    use std::path::PathBuf;
+
+    fn entry(name: &str, url: &str) -> RegistryEntry {
+        RegistryEntry {
+            source: AppSource::File(PathBuf::from(format!("/etc/halos/webapps.d/{}.toml", name))),
+            app: AppDefinition {
+                id: name.to_string(),
+                name: name.to_string(),
+                url: url.to_string(),
+                description: None,
+                icon_url: None,
+                category: None,
+                visible: true,
+                app_type: AppType::default(),
+                ping_url: None,
+                layout: LayoutConfig::default(),
+            },
+        }
+    }
+
+    fn homarr_app(id: &str, name: &str, url: &str) -> SelectableApp {
+        SelectableApp {
+            id: id.to_string(),
+            name: name.to_string(),
+            icon_url: "/icons/docker.svg".to_string(),
+            href: Some(url.to_string()),
+        }
+    }
+
+    fn board(id: &str, app_ids: &[&str]) -> Board {
+        let items: Vec<_> = app_ids
+            .iter()
+            .enumerate()
+            .map(|(x, app_id)| {
+                homarr::app_board_item(
+                    &format!("item-{}", app_id),
+                    app_id,
+                    "layout-1",
+                    "main",
+                    x as i32,
+                    0,
+                    1,
+                    1,
+                )
+            })
+            .collect();
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "name": format!("{}-name", id),
+            "sections": [],
+            "layouts": [],
+            "items": items
+        }))
+        .unwrap()
+    }
+
+    fn state() -> State {
+        let mut state = State {
+            first_boot_completed: true,
+            api_key: Some("secret.token".to_string()),
+            ..Default::default()
+        };
+        state.discovered_apps.insert(
+            "signalk".to_string(),
+            DiscoveredApp {
+                name: "Signal K".to_string(),
+                url: "http://signalk:3000".to_string(),
+                container_id: "signalk-server-container".to_string(),
+                added_at: chrono::Utc::now(),
+                ..Default::default()
+            },
+        );
+        state.mark_removed_from_board("board-1", "b");
+        state.mark_removed_from_board("board-1", "a");
+        state
+    }
+
+    #[test]
+    fn test_state_report_hides_api_key() {
+        let report = state_report(&state());
+
+        assert!(report.get("api_key").is_none());
+        assert_eq!(report["api_key_configured"], serde_json::json!(true));
+        assert!(!report.to_string().contains("secret.token"));
+        assert_eq!(report["first_boot_completed"], serde_json::json!(true));
+        assert_eq!(
+            report["discovered_apps"]["signalk"]["container_id"],
+            serde_json::json!("signalk-server-container")
+        );
+        // Sets are sorted
+        assert_eq!(
+            report["removed_apps_by_board"]["board-1"],
+            serde_json::json!(["a", "b"])
+        );
+    }
+
+    #[test]
+    fn test_state_report_yaml() {
+        let yaml = serde_yaml::to_string(&state_report(&state())).unwrap();
+        assert!(yaml.contains("api_key_configured: true"));
+        assert!(!yaml.contains("secret.token"));
+    }
+
+    #[test]
+    fn test_render_text_shows_full_container_name() {
+        let text = render_text(&state(), None);
+
+        assert!(text.contains(
+            "Signal K (http://signalk:3000) [id: signalk, container: signalk-server-container]"
+        ));
+        assert!(text.contains("board board-1: a, b"));
+        assert!(text.contains("Last sync: never"));
+        assert!(!text.contains("secret.token"));
+    }
+
+    #[test]
+    fn test_drift_report() {
+        let mut state = State::default();
+        for (url, name) in [("http://a", "A"), ("http://gone", "Gone")] {
+            state.discovered_apps.insert(
+                name.to_string(),
+                DiscoveredApp {
+                    name: name.to_string(),
+                    url: url.to_string(),
+                    container_id: String::new(),
+                    added_at: chrono::Utc::now(),
+                    ..Default::default()
+                },
+            );
+        }
+        state.mark_removed_from_board("board-2", "A");
+
+        let inputs = SyncInputs {
+            boards: vec![board("board-1", &["app-gone"]), board("board-2", &[])],
+            existing_apps: vec![
+                homarr_app("app-a", "A (edited)", "http://a"),
+                homarr_app("app-gone", "Gone", "http://gone"),
+                homarr_app("app-c", "C", "http://c-old"),
+            ],
+            apps_fetched: true,
+            entries: vec![
+                entry("A", "http://a"),
+                entry("B", "http://b"),
+                entry("C", "http://c"),
+            ],
+            can_prune: true,
+            relayout: false,
+        };
+
+        let report = drift_report(&inputs, &state);
+
+        let missing: Vec<_> = report.missing_apps.iter().map(|a| a.url.as_str()).collect();
+        assert_eq!(missing, vec!["http://b"]);
+
+        // "A" is on no board; board-2 is excluded because the user removed it there
+        let tiles: Vec<_> = report
+            .missing_tiles
+            .iter()
+            .map(|t| (t.board.as_str(), t.url.as_str()))
+            .collect();
+        assert_eq!(tiles, vec![("board-1-name", "http://a")]);
+
+        assert_eq!(report.orphaned_apps.len(), 1);
+        assert_eq!(report.orphaned_apps[0].url, "http://gone");
+        assert_eq!(report.orphaned_apps[0].boards, vec!["board-1-name"]);
+
+        let mismatches: Vec<_> = report
+            .mismatched_apps
+            .iter()
+            .map(|m| (m.name.as_str(), m.field.as_str(), m.homarr.as_str()))
+            .collect();
+        assert_eq!(
+            mismatches,
+            vec![("A", "name", "A (edited)"), ("C", "url", "http://c-old")]
+        );
+
+        assert_eq!(report.removed_by_user.len(), 1);
+        assert_eq!(report.removed_by_user[0].board, "board-2-name");
+        assert!(report.errors.is_empty());
+        assert!(!report.is_clean());
+
+        let text = render_drift_text(&report);
+        assert!(text.contains("B (http://b) missing in Homarr"));
+        assert!(text.contains("Gone (http://gone) no longer in the registry, on board-1-name"));
+    }
+
+    #[test]
+    fn test_drift_report_clean() {
+        let mut state = State::default();
+        state.discovered_apps.insert(
+            "A".to_string(),
+            DiscoveredApp {
+                name: "A".to_string(),
+                url: "http://a".to_string(),
+                container_id: String::new(),
+                added_at: chrono::Utc::now(),
+                ..Default::default()
+            },
+        );
+        let inputs = SyncInputs {
+            boards: vec![board("board-1", &["app-a"])],
+            existing_apps: vec![homarr_app("app-a", "A", "http://a")],
+            apps_fetched: true,
+            entries: vec![entry("A", "http://a")],
+            can_prune: true,
+            relayout: false,
+        };
+
+        let report = drift_report(&inputs, &state);
+
+        assert!(report.is_clean(), "{:?}", report);
+        assert!(render_drift_text(&report).contains("in sync"));
+    }
+
+    #[test]
+    fn test_render_text_auth_failure() {
+        let mut state = state();
+        state.auth_failure = Some(AuthFailure::BootstrapRejected);
+
+        let text = render_text(&state, None);
+        assert!(text.contains("API key: error: API key and bootstrap key rejected by Homarr"));
+        assert!(text.contains("Bootstrap key: not verified as revoked"));
+
+        let report = state_report(&state);
+        assert_eq!(
+            report["auth_failure"],
+            serde_json::json!("bootstrap_rejected")
+        );
+    }
+
+    #[test]
+    fn test_render_text_live() {
+        let mut live = LiveStatus::default();
+        live.homarr.reachable = true;
+        live.homarr.api_key_valid = Some(false);
+        live.docker.error = Some("socket not found".to_string());
+
+        let text = render_text(&state(), Some(&live));
+
+        assert!(text.contains("Homarr: reachable"));
+        assert!(text.contains("API key: rejected"));
+        assert!(text.contains("Docker: not connected"));
+        assert!(text.contains("Error: socket not found"));
+    }
+}
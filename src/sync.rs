@@ -0,0 +1,1836 @@
+//! Sync planning and execution
+//!
+//! A sync runs in two phases. `build_plan()` compares the registry with the
+//! current Homarr apps and boards and decides what to change, without making
+//! any API calls. `execute_plan()` then applies those decisions. The `plan`
+//! command (and `sync --dry-run`) only run the first phase and print the result.
+
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+use std::path::Path;
+
+use crate::config::Config;
+use crate::docker;
+use crate::error::Result;
+use crate::homarr::{self, Board, HomarrClient, Section, SelectableApp};
+use crate::layout::{self, ManagedTile, OccupancyGrid, TileMove};
+use crate::metrics;
+use crate::registry::{self, AppDefinition, RegistryEntry};
+use crate::state::{DiscoveredApp, State};
+
+/// Everything a sync plan is computed from, fetched once per sync
+pub struct SyncInputs {
+    /// Writable boards with their sections and items
+    pub boards: Vec<Board>,
+
+    /// Apps currently in Homarr's global app registry
+    pub existing_apps: Vec<SelectableApp>,
+
+    /// Whether `existing_apps` was fetched successfully
+    pub apps_fetched: bool,
+
+    /// Registry entries merged with apps discovered from container labels
+    pub entries: Vec<RegistryEntry>,
+
+    /// Whether pruning is safe (every source of truth was read successfully)
+    pub can_prune: bool,
+
+    /// Whether to reflow adapter-owned tiles into priority order
+    pub relayout: bool,
+}
+
+/// Decisions made by a sync, computed before anything is changed in Homarr
+#[derive(Debug, Default, Serialize)]
+pub struct SyncPlan {
+    /// Apps to create in Homarr's global app registry
+    pub create_apps: Vec<AppChange>,
+
+    /// Existing Homarr apps to update from their registry definition
+    pub update_apps: Vec<AppChange>,
+
+    /// Per-board changes
+    pub boards: Vec<BoardPlan>,
+
+    /// Apps whose registry entry or container is gone
+    pub prune_apps: Vec<PruneChange>,
+
+    /// Whether pruning was skipped because a source could not be read
+    pub prune_skipped: bool,
+}
+
+/// An app to create or update in Homarr
+#[derive(Debug, Serialize)]
+pub struct AppChange {
+    /// Registry app ID
+    pub id: String,
+    pub name: String,
+    pub url: String,
+
+    /// Homarr app ID (only for updates)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub app_id: Option<String>,
+
+    /// Registry definition to send to Homarr
+    #[serde(skip)]
+    pub app: AppDefinition,
+}
+
+/// Changes to a single board
+#[derive(Debug, Serialize)]
+pub struct BoardPlan {
+    pub board_id: String,
+    pub board_name: String,
+
+    /// Category sections to create
+    pub new_categories: Vec<String>,
+
+    /// Whether the board's sections change (new or reordered categories)
+    pub sections_changed: bool,
+
+    /// Tiles to add
+    pub additions: Vec<ItemAddition>,
+
+    /// IDs of apps not added because the user removed them from this board
+    pub skipped_removed: Vec<String>,
+
+    /// IDs of apps detected as removed by the user since the last sync
+    pub detected_removals: Vec<String>,
+
+    /// IDs of apps detected as re-added by the user since the last sync
+    pub detected_readds: Vec<String>,
+
+    /// Existing adapter-owned tiles moved into priority order
+    pub moves: Vec<ItemMove>,
+
+    /// Board layout that tile positions apply to
+    #[serde(skip)]
+    pub layout_id: String,
+
+    /// Board sections, including new or reordered categories
+    #[serde(skip)]
+    pub sections: Vec<Section>,
+
+    /// IDs of managed apps on the board before the sync (None if removal
+    /// detection was not possible)
+    #[serde(skip)]
+    pub present: Option<HashSet<String>>,
+
+    /// Board items as fetched for planning; changes are applied on top of
+    /// these and saved in a single `board.saveBoard` call
+    #[serde(skip)]
+    pub items: Vec<serde_json::Value>,
+}
+
+/// A tile to add to a board
+#[derive(Debug, Serialize)]
+pub struct ItemAddition {
+    /// Registry app ID
+    pub id: String,
+    pub app_name: String,
+    pub app_url: String,
+    pub item_id: String,
+    pub section_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub section_name: Option<String>,
+    pub x_offset: i32,
+    pub y_offset: i32,
+    pub width: i32,
+    pub height: i32,
+
+    /// Board layout the position applies to
+    #[serde(skip)]
+    pub layout_id: String,
+}
+
+/// An existing tile moved by a relayout
+#[derive(Debug, Serialize)]
+pub struct ItemMove {
+    pub app_name: String,
+    #[serde(flatten)]
+    pub tile: TileMove,
+}
+
+/// An app to prune from Homarr
+#[derive(Debug, Serialize)]
+pub struct PruneChange {
+    /// Registry app ID
+    pub id: String,
+    pub name: String,
+    pub url: String,
+
+    /// Homarr app ID (None if the app no longer exists in Homarr)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub app_id: Option<String>,
+
+    /// Whether the app itself is deleted. Apps the adapter adopted instead
+    /// of creating only lose their tiles.
+    pub delete_app: bool,
+
+    /// Names of boards the app's tiles are removed from
+    pub boards: Vec<String>,
+
+    /// Board item IDs recorded for the app's tiles
+    #[serde(skip)]
+    pub item_ids: Vec<String>,
+}
+
+/// Outcome of executing a plan
+#[derive(Debug, Default)]
+pub struct SyncReport {
+    pub created: usize,
+    pub updated: usize,
+    pub added: usize,
+    pub moved: usize,
+    pub pruned: usize,
+    pub failed: usize,
+}
+
+impl SyncPlan {
+    /// Check if the plan changes nothing in Homarr
+    ///
+    /// App updates are not counted since every existing app is refreshed on each sync.
+    pub fn is_noop(&self) -> bool {
+        self.create_apps.is_empty()
+            && self.prune_apps.is_empty()
+            && self
+                .boards
+                .iter()
+                .all(|b| b.additions.is_empty() && b.moves.is_empty() && !b.sections_changed)
+    }
+}
+
+/// Fetch the boards, apps and registry entries a plan is built from
+///
+/// Returns `None` if there are no writable boards to sync to.
+pub async fn gather_inputs(client: &HomarrClient, config: &Config) -> Option<SyncInputs> {
+    // Discover writable boards
+    let writable_boards = client.get_writable_boards().await.unwrap_or_else(|e| {
+        tracing::warn!("Failed to fetch writable boards: {}", e);
+        vec![]
+    });
+
+    if writable_boards.is_empty() {
+        tracing::warn!("No writable boards found, skipping sync");
+        return None;
+    }
+
+    tracing::info!(
+        "Found {} writable board(s): {}",
+        writable_boards.len(),
+        writable_boards
+            .iter()
+            .map(|b| b.name.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+
+    // Pruning is only safe when every source of truth was read successfully;
+    // otherwise a transient failure would look like all apps disappearing.
+    let mut can_prune = true;
+
+    // Fetch each board once with its sections and items
+    let mut boards = Vec::new();
+    for board in &writable_boards {
+        match client.get_board(&board.name).await {
+            Ok(board) => boards.push(board),
+            Err(e) => {
+                tracing::warn!("Failed to fetch board '{}', skipping: {}", board.name, e);
+                can_prune = false;
+            }
+        }
+    }
+
+    // Pre-fetch existing apps for efficient deduplication
+    let (existing_apps, apps_fetched) = match client.get_all_apps().await {
+        Ok(apps) => (apps, true),
+        Err(e) => {
+            tracing::warn!("Failed to fetch existing apps: {}", e);
+            can_prune = false;
+            (vec![], false)
+        }
+    };
+
+    // Load registry apps
+    tracing::info!("Loading apps from registry: {}", config.registry_dir);
+    let registry_apps = registry::load_all_apps(&config.registry_dir).unwrap_or_else(|e| {
+        tracing::warn!("Failed to load registry apps: {}", e);
+        can_prune = false;
+        vec![]
+    });
+
+    // Merge in apps discovered from container labels
+    let discovered_apps = if config.docker_labels {
+        discover_container_apps(config).await.unwrap_or_else(|e| {
+            tracing::warn!("Failed to discover apps from Docker labels: {}", e);
+            can_prune = false;
+            vec![]
+        })
+    } else {
+        vec![]
+    };
+
+    let entries = registry::merge_entries(registry_apps, discovered_apps);
+    metrics::set_app_counts(
+        entries.len(),
+        entries.iter().filter(|e| e.app.is_visible()).count(),
+    );
+
+    Some(SyncInputs {
+        boards,
+        existing_apps,
+        apps_fetched,
+        entries,
+        can_prune,
+        relayout: config.relayout_on_sync,
+    })
+}
+
+/// Discover apps from containers with `homarr.*` labels
+async fn discover_container_apps(config: &Config) -> Result<Vec<RegistryEntry>> {
+    let docker = docker::connect(&config.docker_socket)?;
+    docker::discover_apps(&docker).await
+}
+
+/// Decide what a sync should change
+///
+/// Makes no API calls. Removal tracking in `state` is updated as a side
+/// effect (tiles the user removed or re-added since the last sync), so
+/// callers that only preview the plan must not save the state.
+pub fn build_plan(state: &mut State, inputs: &SyncInputs) -> SyncPlan {
+    let mut plan = SyncPlan::default();
+
+    // Filter to visible apps only
+    let visible_apps: Vec<&RegistryEntry> = inputs
+        .entries
+        .iter()
+        .filter(|e| e.app.is_visible())
+        .collect();
+    let hidden_count = inputs.entries.len() - visible_apps.len();
+    if hidden_count > 0 {
+        tracing::debug!(
+            "Filtered out {} hidden app(s) from {} total",
+            hidden_count,
+            inputs.entries.len()
+        );
+    }
+
+    // State written before apps had IDs is keyed by URL until now
+    let mut ids_by_url: HashMap<&str, &str> = inputs
+        .entries
+        .iter()
+        .map(|e| (e.app.url.as_str(), e.app.id.as_str()))
+        .collect();
+    state.adopt_app_ids(&ids_by_url);
+    adopt_legacy_items(state, &inputs.boards, &visible_apps);
+
+    let active_ids: HashSet<&str> = visible_apps.iter().map(|e| e.app.id.as_str()).collect();
+
+    // Create apps that don't exist yet, update the others
+    let mut app_ids: HashMap<String, String> = HashMap::new();
+    for entry in &visible_apps {
+        let app = &entry.app;
+        let app_id = if inputs.apps_fetched {
+            find_app(state, &inputs.existing_apps, &app.id, &app.url).map(|a| a.id.clone())
+        } else {
+            // Without the app list, trust the recorded ID rather than create a duplicate
+            state.app_id(&app.id).map(str::to_string)
+        };
+        let change = AppChange {
+            id: app.id.clone(),
+            name: app.name.clone(),
+            url: app.url.clone(),
+            app_id: app_id.clone(),
+            app: app.clone(),
+        };
+        match app_id {
+            Some(app_id) => {
+                app_ids.insert(app.id.clone(), app_id);
+                plan.update_apps.push(change);
+            }
+            None => plan.create_apps.push(change),
+        }
+    }
+
+    // Map board tiles back to registry apps; recorded IDs win over matching
+    // hrefs. Removal detection needs the mapping for every tracked app, which
+    // the recorded IDs alone provide once each tracked app has one.
+    for (id, app) in &state.discovered_apps {
+        ids_by_url.entry(app.url.as_str()).or_insert(id.as_str());
+    }
+    let mut ids_by_app_id: HashMap<String, String> = HashMap::new();
+    if inputs.apps_fetched {
+        for app in &inputs.existing_apps {
+            if let Some(id) = app.href.as_deref().and_then(|href| ids_by_url.get(href)) {
+                ids_by_app_id.insert(app.id.clone(), id.to_string());
+            }
+        }
+    }
+    for (id, app) in &state.discovered_apps {
+        if let Some(app_id) = &app.app_id {
+            ids_by_app_id.insert(app_id.clone(), id.clone());
+        }
+    }
+    let can_detect = inputs.apps_fetched
+        || state
+            .discovered_apps
+            .values()
+            .all(|app| app.app_id.is_some());
+
+    let categories = registry::categories_by_priority(&visible_apps);
+
+    for board in &inputs.boards {
+        plan.boards.push(plan_board(
+            state,
+            board,
+            &visible_apps,
+            &active_ids,
+            &categories,
+            &app_ids,
+            can_detect.then_some(&ids_by_app_id),
+            inputs.relayout,
+        ));
+    }
+
+    // Prune apps whose registry entry or container is gone
+    if inputs.can_prune {
+        let mut stale_ids: Vec<&String> = state
+            .discovered_apps
+            .keys()
+            .filter(|id| !active_ids.contains(id.as_str()))
+            .collect();
+        stale_ids.sort();
+
+        for id in stale_ids {
+            let tracked = &state.discovered_apps[id];
+            let app_id =
+                find_app(state, &inputs.existing_apps, id, &tracked.url).map(|a| a.id.clone());
+            let item_ids: Vec<String> = tracked.board_items.values().cloned().collect();
+            let boards = inputs
+                .boards
+                .iter()
+                .filter(|b| {
+                    app_id
+                        .as_deref()
+                        .is_some_and(|id| homarr::board_has_app(&b.items, id))
+                        || homarr::board_app_items(&b.items)
+                            .iter()
+                            .any(|(item_id, _)| item_ids.iter().any(|id| id == item_id))
+                })
+                .map(|b| b.name.clone())
+                .collect();
+
+            plan.prune_apps.push(PruneChange {
+                id: id.clone(),
+                name: tracked.name.clone(),
+                url: tracked.url.clone(),
+                app_id,
+                delete_app: tracked.created_by_adapter,
+                boards,
+                item_ids,
+            });
+        }
+    } else {
+        plan.prune_skipped = true;
+    }
+
+    plan
+}
+
+/// Find the Homarr app for a registry app
+///
+/// Prefers the Homarr app ID recorded in state, so an app is still found
+/// after its href was edited in Homarr. Falls back to matching the href with
+/// the app's URL, then with the URL recorded at the last sync (the app's URL
+/// changed in the registry since).
+pub fn find_app<'a>(
+    state: &State,
+    apps: &'a [SelectableApp],
+    id: &str,
+    url: &str,
+) -> Option<&'a SelectableApp> {
+    state
+        .app_id(id)
+        .and_then(|app_id| apps.iter().find(|app| app.id == app_id))
+        .or_else(|| HomarrClient::find_app_in_list(apps, url))
+        .or_else(|| {
+            state
+                .discovered_apps
+                .get(id)
+                .and_then(|tracked| HomarrClient::find_app_in_list(apps, &tracked.url))
+        })
+}
+
+/// Record tiles that still carry the item ID of an earlier version
+///
+/// Item IDs of apps without a container used to be generated with
+/// `DefaultHasher`. Recording those tiles in state keeps them recognised, so
+/// they are neither duplicated nor left out of relayouts.
+fn adopt_legacy_items(state: &mut State, boards: &[Board], visible_apps: &[&RegistryEntry]) {
+    let mut adopted = 0;
+    for board in boards {
+        for entry in visible_apps {
+            let app = &entry.app;
+            if !state.discovered_apps.contains_key(&app.id)
+                || state.board_item_id(&board.id, &app.id).is_some()
+            {
+                continue;
+            }
+            let Some(legacy_id) = homarr::legacy_board_item_id(app) else {
+                continue;
+            };
+            if legacy_id == homarr::board_item_id(app) {
+                continue;
+            }
+            let on_board = board
+                .items
+                .iter()
+                .any(|item| item.get("id").and_then(|i| i.as_str()) == Some(legacy_id.as_str()));
+            if on_board {
+                state.record_board_item(&board.id, &app.id, &legacy_id);
+                adopted += 1;
+            }
+        }
+    }
+
+    if adopted > 0 {
+        tracing::info!("Recorded {} tile(s) with legacy board item IDs", adopted);
+    }
+}
+
+/// Board item ID of an app's tile: the one recorded in state, otherwise the
+/// one the adapter generates for new tiles
+fn owned_item_id(state: &State, board_id: &str, app: &AppDefinition) -> String {
+    state
+        .board_item_id(board_id, &app.id)
+        .map(str::to_string)
+        .unwrap_or_else(|| homarr::board_item_id(app))
+}
+
+/// Decide how to reflow adapter-owned tiles on every board, without adding,
+/// removing or updating anything else
+///
+/// Tiles with legacy item IDs are recorded in `state` as a side effect.
+pub fn build_relayout_plan(state: &mut State, inputs: &SyncInputs) -> SyncPlan {
+    let visible_apps: Vec<&RegistryEntry> = inputs
+        .entries
+        .iter()
+        .filter(|e| e.app.is_visible())
+        .collect();
+    adopt_legacy_items(state, &inputs.boards, &visible_apps);
+
+    let boards = inputs
+        .boards
+        .iter()
+        .map(|board| {
+            let mut board_plan = BoardPlan::new(board);
+            board_plan.moves = plan_moves(
+                &board.items,
+                &visible_apps,
+                state,
+                &board.id,
+                &board_plan.layout_id,
+                column_count(board),
+            );
+            board_plan
+        })
+        .collect();
+
+    SyncPlan {
+        boards,
+        ..Default::default()
+    }
+}
+
+/// Number of grid columns in the board layout tiles are placed in
+fn column_count(board: &Board) -> i32 {
+    board
+        .layouts
+        .first()
+        .map(|l| l.column_count)
+        .unwrap_or(layout::DEFAULT_COLUMN_COUNT)
+}
+
+/// Compute moves that put the adapter-owned tiles in `items` in priority order
+///
+/// Tiles are identified by their board item ID, so tiles planned in this
+/// sync (which have no app ID yet) are included.
+fn plan_moves(
+    items: &[serde_json::Value],
+    visible_apps: &[&RegistryEntry],
+    state: &State,
+    board_id: &str,
+    layout_id: &str,
+    column_count: i32,
+) -> Vec<ItemMove> {
+    let mut names: HashMap<String, &str> = HashMap::new();
+    let mut managed: HashMap<String, ManagedTile> = HashMap::new();
+    for (rank, entry) in visible_apps.iter().enumerate() {
+        let item_id = owned_item_id(state, board_id, &entry.app);
+        let layout = entry.app.effective_layout();
+        let pinned = match (layout.x_offset, layout.y_offset) {
+            (Some(x), Some(y)) => Some((x as i32, y as i32)),
+            _ => None,
+        };
+        names.insert(item_id.clone(), &entry.app.name);
+        managed.insert(item_id, ManagedTile { rank, pinned });
+    }
+
+    layout::reflow(items, layout_id, column_count, &managed)
+        .into_iter()
+        .map(|tile| ItemMove {
+            app_name: names[&tile.item_id].to_string(),
+            tile,
+        })
+        .collect()
+}
+
+impl BoardPlan {
+    /// Create an empty plan for a board
+    fn new(board: &Board) -> Self {
+        Self {
+            board_id: board.id.clone(),
+            board_name: board.name.clone(),
+            new_categories: vec![],
+            sections_changed: false,
+            additions: vec![],
+            skipped_removed: vec![],
+            detected_removals: vec![],
+            detected_readds: vec![],
+            moves: vec![],
+            layout_id: board
+                .layouts
+                .first()
+                .map(|l| l.id.clone())
+                .unwrap_or_default(),
+            sections: board.sections.clone(),
+            present: None,
+            items: board.items.clone(),
+        }
+    }
+}
+
+/// Plan the changes to one board
+#[allow(clippy::too_many_arguments)]
+fn plan_board(
+    state: &mut State,
+    board: &Board,
+    visible_apps: &[&RegistryEntry],
+    active_ids: &HashSet<&str>,
+    categories: &[(String, u8)],
+    app_ids: &HashMap<String, String>,
+    ids_by_app_id: Option<&HashMap<String, String>>,
+    relayout: bool,
+) -> BoardPlan {
+    let mut board_plan = BoardPlan::new(board);
+    let board_items = homarr::board_app_items(&board.items);
+    let has_item = |item_id: Option<&str>| {
+        item_id.is_some_and(|item_id| board_items.iter().any(|(id, _)| *id == item_id))
+    };
+
+    // Detect tiles the user removed or re-added since the last sync
+    if let Some(ids_by_app_id) = ids_by_app_id {
+        let mut present: HashSet<String> = board_items
+            .iter()
+            .filter_map(|(_, app_id)| ids_by_app_id.get(*app_id))
+            .filter(|id| {
+                state.discovered_apps.contains_key(id.as_str()) || active_ids.contains(id.as_str())
+            })
+            .cloned()
+            .collect();
+        present.extend(
+            state
+                .discovered_apps
+                .keys()
+                .filter(|id| has_item(state.board_item_id(&board.id, id)))
+                .cloned(),
+        );
+
+        let (removed, readded) = state.detect_board_changes(&board.id, &present, active_ids);
+        board_plan.detected_removals = removed;
+        board_plan.detected_readds = readded;
+        board_plan.present = Some(present);
+    }
+
+    // Make sure each registry category has a section
+    let existing_categories: Vec<String> = board
+        .sections
+        .iter()
+        .filter_map(|s| s.name.clone())
+        .collect();
+    board_plan.sections_changed =
+        homarr::arrange_category_sections(&mut board_plan.sections, categories);
+    board_plan.new_categories = categories
+        .iter()
+        .filter(|(name, _)| {
+            !existing_categories
+                .iter()
+                .any(|existing| existing.eq_ignore_ascii_case(name))
+        })
+        .map(|(name, _)| name.clone())
+        .collect();
+
+    let layout_id = board_plan.layout_id.clone();
+    let column_count = column_count(board);
+
+    // Whether each planned addition still needs a position
+    let mut auto_placed = Vec::new();
+
+    for entry in visible_apps {
+        let app = &entry.app;
+
+        // Check if app was removed from this specific board
+        if state.is_removed_from_board(&board.id, &app.id) {
+            tracing::debug!(
+                "App '{}' was removed from board '{}', skipping",
+                app.name,
+                board.name
+            );
+            board_plan.skipped_removed.push(app.id.clone());
+            continue;
+        }
+
+        // Check if this app is already on the board
+        let on_board = app_ids
+            .get(&app.id)
+            .is_some_and(|app_id| homarr::board_has_app(&board.items, app_id))
+            || has_item(state.board_item_id(&board.id, &app.id));
+        if on_board {
+            continue;
+        }
+
+        let section = homarr::section_for_app(&board_plan.sections, app.category.as_deref());
+        let section_id = section.map(|s| s.id.clone()).unwrap_or_default();
+        let section_name = section.and_then(|s| s.name.clone());
+
+        // Get layout preferences from registry
+        let layout = app.effective_layout();
+        let width = (layout.width as i32).clamp(1, column_count.max(1));
+        let height = layout.height as i32;
+
+        // Use explicit position if provided, otherwise auto-position below
+        let position = match (layout.x_offset, layout.y_offset) {
+            (Some(x), Some(y)) => Some((x as i32, y as i32)),
+            _ => None,
+        };
+        auto_placed.push(position.is_none());
+        let (x_offset, y_offset) = position.unwrap_or_default();
+
+        board_plan.additions.push(ItemAddition {
+            id: app.id.clone(),
+            app_name: app.name.clone(),
+            app_url: app.url.clone(),
+            item_id: homarr::board_item_id(app),
+            section_id,
+            section_name,
+            x_offset,
+            y_offset,
+            width,
+            height,
+            layout_id: layout_id.clone(),
+        });
+    }
+
+    // Occupancy grid per section, built from the board's current items
+    let mut grids: HashMap<String, OccupancyGrid> = HashMap::new();
+
+    // Reserve explicit positions first, then pack the remaining tiles into
+    // the first free spot in priority order
+    for (addition, _) in board_plan
+        .additions
+        .iter()
+        .zip(&auto_placed)
+        .filter(|(_, auto)| !**auto)
+    {
+        section_grid(
+            &mut grids,
+            board,
+            &addition.section_id,
+            &layout_id,
+            column_count,
+        )
+        .occupy(
+            addition.x_offset,
+            addition.y_offset,
+            addition.width,
+            addition.height,
+        );
+    }
+    for (addition, _) in board_plan
+        .additions
+        .iter_mut()
+        .zip(&auto_placed)
+        .filter(|(_, auto)| **auto)
+    {
+        (addition.x_offset, addition.y_offset) = section_grid(
+            &mut grids,
+            board,
+            &addition.section_id,
+            &layout_id,
+            column_count,
+        )
+        .place(addition.width, addition.height);
+    }
+
+    // Reflow the board including the planned tiles; new tiles take their
+    // reflowed position directly, existing ones are moved
+    if relayout {
+        let mut items = board.items.clone();
+        items.extend(board_plan.additions.iter().map(|a| {
+            homarr::app_board_item(
+                &a.item_id,
+                "",
+                &a.layout_id,
+                &a.section_id,
+                a.x_offset,
+                a.y_offset,
+                a.width,
+                a.height,
+            )
+        }));
+
+        for item_move in plan_moves(
+            &items,
+            visible_apps,
+            state,
+            &board.id,
+            &layout_id,
+            column_count,
+        ) {
+            match board_plan
+                .additions
+                .iter_mut()
+                .find(|a| a.item_id == item_move.tile.item_id)
+            {
+                Some(addition) => {
+                    addition.x_offset = item_move.tile.x_offset;
+                    addition.y_offset = item_move.tile.y_offset;
+                }
+                None => board_plan.moves.push(item_move),
+            }
+        }
+    }
+
+    board_plan
+}
+
+/// Get the occupancy grid for a board section, building it on first use
+fn section_grid<'a>(
+    grids: &'a mut HashMap<String, OccupancyGrid>,
+    board: &Board,
+    section_id: &str,
+    layout_id: &str,
+    column_count: i32,
+) -> &'a mut OccupancyGrid {
+    grids.entry(section_id.to_string()).or_insert_with(|| {
+        OccupancyGrid::from_items(&board.items, section_id, layout_id, column_count)
+    })
+}
+
+/// Apply a plan to Homarr and record the results in `state`
+///
+/// All changes to a board (sections, new tiles, pruned tiles) are applied in
+/// memory and saved with one `board.saveBoard` call per changed board.
+/// Failures are logged and counted; the remaining changes are still applied.
+///
+/// State is saved to `state_file` after each app created, board saved and
+/// app pruned, so a sync cut off part way (e.g. by the watchdog deadline)
+/// still has every change it made in Homarr recorded.
+pub async fn execute_plan(
+    client: &HomarrClient,
+    state: &mut State,
+    plan: &SyncPlan,
+    state_file: &Path,
+) -> SyncReport {
+    let mut report = SyncReport::default();
+
+    // Create and update apps, collecting app IDs for the board items
+    let mut app_ids: HashMap<&str, String> = HashMap::new();
+
+    for change in &plan.create_apps {
+        track_app(state, &change.app);
+        match client.create_registry_app(&change.app).await {
+            Ok(app_id) => {
+                report.created += 1;
+                state.record_created_app(&change.id, &app_id);
+                checkpoint(state, state_file);
+                app_ids.insert(&change.id, app_id);
+            }
+            Err(e) => {
+                tracing::warn!("Failed to create app '{}': {}", change.name, e);
+                report.failed += 1;
+            }
+        }
+    }
+
+    for change in &plan.update_apps {
+        track_app(state, &change.app);
+        let Some(app_id) = change.app_id.as_deref() else {
+            continue;
+        };
+        match client.update_registry_app(app_id, &change.app).await {
+            Ok(()) => {
+                report.updated += 1;
+                state.record_app_id(&change.id, app_id);
+                app_ids.insert(&change.id, app_id.to_string());
+            }
+            Err(e) => {
+                tracing::warn!("Failed to update app '{}': {}", change.name, e);
+                report.failed += 1;
+            }
+        }
+    }
+
+    let prune_app_ids: HashSet<&str> = plan
+        .prune_apps
+        .iter()
+        .filter_map(|p| p.app_id.as_deref())
+        .collect();
+    let prune_item_ids: HashSet<&str> = plan
+        .prune_apps
+        .iter()
+        .flat_map(|p| p.item_ids.iter().map(String::as_str))
+        .collect();
+
+    // Apply all changes to each board and save it once
+    let mut failed_boards: HashSet<&str> = HashSet::new();
+    for board in &plan.boards {
+        let applied = apply_board_plan(board, &app_ids, &prune_app_ids, &prune_item_ids);
+        let mut seen = board.present.clone();
+        let mut saved = false;
+
+        if applied.changed {
+            let updated = Board {
+                id: board.board_id.clone(),
+                name: board.board_name.clone(),
+                sections: board.sections.clone(),
+                layouts: vec![],
+                items: applied.items.clone(),
+            };
+
+            match client.save_board(&updated).await {
+                Ok(()) => {
+                    saved = true;
+                    report.added += applied.added.len();
+                    report.moved += applied.moved;
+                    if let Some(seen) = seen.as_mut() {
+                        seen.extend(applied.added.iter().cloned());
+                    }
+                    tracing::info!(
+                        "Saved board '{}': {} tile(s) added, {} moved, {} removed",
+                        board.board_name,
+                        applied.added.len(),
+                        applied.moved,
+                        applied.removed
+                    );
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to save board '{}': {}", board.board_name, e);
+                    report.failed += 1;
+                    failed_boards.insert(&board.board_name);
+                }
+            }
+        }
+
+        // Remember what is on the board for the next sync's removal detection
+        if let Some(seen) = seen {
+            state.board_apps_seen.insert(board.board_id.clone(), seen);
+        }
+        let items = if saved { &applied.items } else { &board.items };
+        state.record_board_items(&board.board_id, &homarr::board_app_items(items));
+        if saved {
+            checkpoint(state, state_file);
+        }
+    }
+
+    // Delete pruned apps once their tiles are gone from every board.
+    // Apps that fail to prune stay tracked so the next sync retries them.
+    for prune in &plan.prune_apps {
+        if let Some(board_name) = prune
+            .boards
+            .iter()
+            .find(|b| failed_boards.contains(b.as_str()))
+        {
+            tracing::warn!(
+                "Not pruning app '{}': its tile could not be removed from board '{}'",
+                prune.name,
+                board_name
+            );
+            continue;
+        }
+
+        match prune.app_id.as_deref() {
+            Some(app_id) if prune.delete_app => {
+                if let Err(e) = client.delete_app(app_id).await {
+                    tracing::warn!("Failed to delete app '{}': {}", prune.name, e);
+                    report.failed += 1;
+                    continue;
+                }
+            }
+            Some(_) => tracing::info!(
+                "Keeping app '{}' in Homarr: it was not created by the adapter",
+                prune.name
+            ),
+            None => tracing::debug!(
+                "App '{}' no longer exists in Homarr, forgetting it",
+                prune.name
+            ),
+        }
+
+        tracing::info!("Pruned app '{}' ({})", prune.name, prune.url);
+        state.record_pruned(&prune.id);
+        checkpoint(state, state_file);
+        report.pruned += 1;
+    }
+
+    report
+}
+
+/// Save state after a change in Homarr
+///
+/// A failure is only logged: the caller saves state again at the end.
+fn checkpoint(state: &State, state_file: &Path) {
+    if let Err(e) = state.save(state_file) {
+        tracing::warn!("Failed to save state: {}", e);
+    }
+}
+
+/// Result of applying a board plan to its items in memory
+struct AppliedBoard {
+    /// Items to save
+    items: Vec<serde_json::Value>,
+
+    /// IDs of apps whose tiles were added
+    added: Vec<String>,
+
+    /// Number of existing tiles moved into priority order
+    moved: usize,
+
+    /// Number of tiles removed for pruned apps
+    removed: usize,
+
+    /// Whether the board needs to be saved
+    changed: bool,
+}
+
+/// Apply planned additions and prune removals to a board's items
+///
+/// Additions whose app has no ID (creation failed) are skipped, and
+/// `board_has_app` still guards against adding an app twice. Tiles of pruned
+/// apps are removed by app ID or by their recorded item ID.
+fn apply_board_plan(
+    board: &BoardPlan,
+    app_ids: &HashMap<&str, String>,
+    prune_app_ids: &HashSet<&str>,
+    prune_item_ids: &HashSet<&str>,
+) -> AppliedBoard {
+    let mut items = board.items.clone();
+    let mut added = Vec::new();
+
+    let moves: Vec<TileMove> = board.moves.iter().map(|m| m.tile.clone()).collect();
+    layout::apply_moves(&mut items, &board.layout_id, &moves);
+
+    for addition in &board.additions {
+        let Some(app_id) = app_ids.get(addition.id.as_str()) else {
+            continue;
+        };
+
+        // Check if this app is already on the board
+        if homarr::board_has_app(&items, app_id) {
+            tracing::info!(
+                "App '{}' already on board '{}', skipping",
+                addition.app_name,
+                board.board_name
+            );
+            continue;
+        }
+
+        items.push(homarr::app_board_item(
+            &addition.item_id,
+            app_id,
+            &addition.layout_id,
+            &addition.section_id,
+            addition.x_offset,
+            addition.y_offset,
+            addition.width,
+            addition.height,
+        ));
+        added.push(addition.id.clone());
+        tracing::debug!(
+            "Adding app '{}' to board '{}' at ({}, {}) size {}x{}",
+            addition.app_name,
+            board.board_name,
+            addition.x_offset,
+            addition.y_offset,
+            addition.width,
+            addition.height
+        );
+    }
+
+    let before = items.len();
+    items.retain(|item| {
+        !homarr::board_app_items(std::slice::from_ref(item))
+            .iter()
+            .any(|(item_id, app_id)| {
+                prune_app_ids.contains(app_id) || prune_item_ids.contains(item_id)
+            })
+    });
+    let removed = before - items.len();
+
+    AppliedBoard {
+        changed: board.sections_changed || !added.is_empty() || !moves.is_empty() || removed > 0,
+        items,
+        added,
+        moved: moves.len(),
+        removed,
+    }
+}
+
+/// Track an app in `discovered_apps`
+///
+/// Keeps the recorded Homarr IDs and the time the app was first added.
+fn track_app(state: &mut State, app: &AppDefinition) {
+    let container_id = app.container_name().unwrap_or("").to_string();
+    state.pruned_apps.remove(&app.id);
+    let tracked = state
+        .discovered_apps
+        .entry(app.id.clone())
+        .or_insert_with(|| DiscoveredApp {
+            added_at: chrono::Utc::now(),
+            ..Default::default()
+        });
+    if !tracked.url.is_empty() && tracked.url != app.url {
+        tracing::info!(
+            "App '{}' moved from {} to {}, updating it in place",
+            app.name,
+            tracked.url,
+            app.url
+        );
+    }
+    tracked.name = app.name.clone();
+    tracked.url = app.url.clone();
+    tracked.container_id = container_id;
+}
+
+/// Render a plan as human-readable text
+pub fn render_text(plan: &SyncPlan) -> String {
+    let mut out = String::new();
+
+    if plan.is_noop() {
+        let _ = writeln!(out, "Homarr is up to date, nothing to add or prune.");
+    }
+
+    if !plan.create_apps.is_empty() {
+        let _ = writeln!(out, "Apps to create ({}):", plan.create_apps.len());
+        for app in &plan.create_apps {
+            let _ = writeln!(out, "  + {} ({})", app.name, app.url);
+        }
+    }
+
+    if !plan.update_apps.is_empty() {
+        let _ = writeln!(out, "Apps to update ({}):", plan.update_apps.len());
+        for app in &plan.update_apps {
+            let _ = writeln!(
+                out,
+                "  ~ {} ({}) [app_id: {}]",
+                app.name,
+                app.url,
+                app.app_id.as_deref().unwrap_or("?")
+            );
+        }
+    }
+
+    for board in &plan.boards {
+        let has_changes = board.sections_changed
+            || !board.additions.is_empty()
+            || !board.moves.is_empty()
+            || !board.skipped_removed.is_empty()
+            || !board.detected_removals.is_empty()
+            || !board.detected_readds.is_empty();
+        if !has_changes {
+            continue;
+        }
+
+        let _ = writeln!(out, "Board '{}':", board.board_name);
+        for category in &board.new_categories {
+            let _ = writeln!(out, "  + category section '{}'", category);
+        }
+        if board.sections_changed && board.new_categories.is_empty() {
+            let _ = writeln!(out, "  ~ reorder category sections");
+        }
+        for addition in &board.additions {
+            let section = addition
+                .section_name
+                .as_deref()
+                .map(|name| format!(" in '{}'", name))
+                .unwrap_or_default();
+            let _ = writeln!(
+                out,
+                "  + {} at ({}, {}) size {}x{}{}",
+                addition.app_name,
+                addition.x_offset,
+                addition.y_offset,
+                addition.width,
+                addition.height,
+                section
+            );
+        }
+        for item_move in &board.moves {
+            let tile = &item_move.tile;
+            let _ = writeln!(
+                out,
+                "  ~ {} moved from ({}, {}) to ({}, {})",
+                item_move.app_name, tile.from_x, tile.from_y, tile.x_offset, tile.y_offset
+            );
+        }
+        for id in &board.detected_removals {
+            let _ = writeln!(out, "  ! {} removed by user, will not re-add", id);
+        }
+        for id in &board.detected_readds {
+            let _ = writeln!(out, "  ! {} re-added by user", id);
+        }
+        for id in &board.skipped_removed {
+            let _ = writeln!(out, "  = {} skipped (removed by user)", id);
+        }
+    }
+
+    if !plan.prune_apps.is_empty() {
+        let _ = writeln!(out, "Apps to prune ({}):", plan.prune_apps.len());
+        for app in &plan.prune_apps {
+            let boards = if app.boards.is_empty() {
+                String::new()
+            } else {
+                format!(" from {}", app.boards.join(", "))
+            };
+            let keep = if app.delete_app || app.app_id.is_none() {
+                ""
+            } else {
+                ", keeping the app"
+            };
+            let _ = writeln!(out, "  - {} ({}){}{}", app.name, app.url, boards, keep);
+        }
+    }
+
+    if plan.prune_skipped {
+        let _ = writeln!(
+            out,
+            "Pruning skipped: app sources could not be read completely."
+        );
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::{AppSource, AppType, LayoutConfig};
+    use serde_json::json;
+    use std::path::PathBuf;
+
+    fn entry(name: &str, url: &str, priority: u8) -> RegistryEntry {
+        RegistryEntry {
+            source: AppSource::File(PathBuf::from(format!("/etc/halos/webapps.d/{}.toml", name))),
+            app: AppDefinition {
+                id: name.to_string(),
+                name: name.to_string(),
+                url: url.to_string(),
+                description: None,
+                icon_url: None,
+                category: None,
+                visible: true,
+                app_type: AppType::default(),
+                ping_url: None,
+                layout: LayoutConfig {
+                    priority,
+                    ..Default::default()
+                },
+            },
+        }
+    }
+
+    fn board(id: &str, items: Vec<serde_json::Value>) -> Board {
+        serde_json::from_value(json!({
+            "id": id,
+            "name": format!("{}-name", id),
+            "sections": [{"id": "main", "kind": "empty", "yOffset": 0, "xOffset": 0}],
+            "layouts": [{"id": "layout-1", "name": "Base", "columnCount": 12, "breakpoint": 0}],
+            "items": items
+        }))
+        .unwrap()
+    }
+
+    fn existing_app(id: &str, url: &str) -> SelectableApp {
+        SelectableApp {
+            id: id.to_string(),
+            name: id.to_string(),
+            icon_url: String::new(),
+            href: Some(url.to_string()),
+        }
+    }
+
+    fn item(app_id: &str, x: i32) -> serde_json::Value {
+        homarr::app_board_item(
+            &format!("item-{}", app_id),
+            app_id,
+            "layout-1",
+            "main",
+            x,
+            0,
+            1,
+            1,
+        )
+    }
+
+    fn inputs(
+        entries: Vec<RegistryEntry>,
+        boards: Vec<Board>,
+        apps: Vec<SelectableApp>,
+    ) -> SyncInputs {
+        SyncInputs {
+            boards,
+            existing_apps: apps,
+            apps_fetched: true,
+            entries,
+            can_prune: true,
+            relayout: false,
+        }
+    }
+
+    fn discovered(state: &mut State, url: &str, name: &str) {
+        // Registry app IDs of the test entries are their names
+        state.discovered_apps.insert(
+            name.to_string(),
+            DiscoveredApp {
+                name: name.to_string(),
+                url: url.to_string(),
+                container_id: String::new(),
+                added_at: chrono::Utc::now(),
+                ..Default::default()
+            },
+        );
+    }
+
+    #[test]
+    fn test_build_plan_creates_and_places_new_apps() {
+        let mut state = State::default();
+        let inputs = inputs(
+            vec![entry("a", "http://a", 10), entry("b", "http://b", 20)],
+            vec![board("board-1", vec![])],
+            vec![],
+        );
+
+        let plan = build_plan(&mut state, &inputs);
+
+        assert_eq!(plan.create_apps.len(), 2);
+        assert!(plan.update_apps.is_empty());
+        let additions = &plan.boards[0].additions;
+        assert_eq!(additions.len(), 2);
+        // Planned tiles don't overlap each other
+        assert_eq!((additions[0].x_offset, additions[0].y_offset), (0, 0));
+        assert_eq!((additions[1].x_offset, additions[1].y_offset), (1, 0));
+        assert_eq!(additions[0].section_id, "main");
+        assert_eq!(additions[0].layout_id, "layout-1");
+        assert!(!plan.is_noop());
+    }
+
+    #[test]
+    fn test_build_plan_packs_tiles_using_board_column_count() {
+        let mut state = State::default();
+        let mut wide = entry("wide", "http://wide", 10);
+        wide.app.layout.width = 2;
+        wide.app.layout.height = 2;
+        let mut pinned = entry("pinned", "http://pinned", 90);
+        pinned.app.layout.x_offset = Some(2);
+        pinned.app.layout.y_offset = Some(0);
+        let mut board = board("board-1", vec![item("user-app", 3)]);
+        board.layouts[0].column_count = 4;
+        let inputs = inputs(
+            vec![
+                wide,
+                entry("a", "http://a", 20),
+                entry("b", "http://b", 30),
+                pinned,
+            ],
+            vec![board],
+            vec![],
+        );
+
+        let plan = build_plan(&mut state, &inputs);
+
+        let positions: Vec<_> = plan.boards[0]
+            .additions
+            .iter()
+            .map(|a| (a.app_name.as_str(), a.x_offset, a.y_offset))
+            .collect();
+        // The pinned tile is reserved first; the rest fill holes in priority order
+        assert_eq!(
+            positions,
+            vec![("wide", 0, 0), ("a", 2, 1), ("b", 3, 1), ("pinned", 2, 0)]
+        );
+    }
+
+    #[test]
+    fn test_build_plan_updates_existing_app_already_on_board() {
+        let mut state = State::default();
+        let inputs = inputs(
+            vec![entry("a", "http://a", 10)],
+            vec![board("board-1", vec![item("app-a", 0)])],
+            vec![existing_app("app-a", "http://a")],
+        );
+
+        let plan = build_plan(&mut state, &inputs);
+
+        assert!(plan.create_apps.is_empty());
+        assert_eq!(plan.update_apps.len(), 1);
+        assert_eq!(plan.update_apps[0].app_id.as_deref(), Some("app-a"));
+        assert!(plan.boards[0].additions.is_empty());
+        assert!(plan.is_noop());
+    }
+
+    #[test]
+    fn test_build_plan_skips_apps_removed_from_board() {
+        let mut state = State::default();
+        state.mark_removed_from_board("board-1", "a");
+        let inputs = inputs(
+            vec![entry("a", "http://a", 10)],
+            vec![board("board-1", vec![]), board("board-2", vec![])],
+            vec![],
+        );
+
+        let plan = build_plan(&mut state, &inputs);
+
+        assert!(plan.boards[0].additions.is_empty());
+        assert_eq!(plan.boards[0].skipped_removed, vec!["a".to_string()]);
+        assert_eq!(plan.boards[1].additions.len(), 1);
+    }
+
+    #[test]
+    fn test_build_plan_detects_user_removal() {
+        let mut state = State::default();
+        discovered(&mut state, "http://a", "a");
+        state
+            .board_apps_seen
+            .insert("board-1".to_string(), ["a".to_string()].into());
+        let inputs = inputs(
+            vec![entry("a", "http://a", 10)],
+            vec![board("board-1", vec![])],
+            vec![existing_app("app-a", "http://a")],
+        );
+
+        let plan = build_plan(&mut state, &inputs);
+
+        assert_eq!(plan.boards[0].detected_removals, vec!["a".to_string()]);
+        assert!(plan.boards[0].additions.is_empty());
+        assert!(state.is_removed_from_board("board-1", "a"));
+    }
+
+    #[test]
+    fn test_build_plan_prunes_stale_apps() {
+        let mut state = State::default();
+        discovered(&mut state, "http://gone", "Gone");
+        state.record_created_app("Gone", "app-gone");
+        let inputs = inputs(
+            vec![],
+            vec![
+                board("board-1", vec![item("app-gone", 0)]),
+                board("board-2", vec![]),
+            ],
+            vec![existing_app("app-gone", "http://gone")],
+        );
+
+        let plan = build_plan(&mut state, &inputs);
+
+        assert_eq!(plan.prune_apps.len(), 1);
+        assert_eq!(plan.prune_apps[0].app_id.as_deref(), Some("app-gone"));
+        assert!(plan.prune_apps[0].delete_app);
+        assert_eq!(plan.prune_apps[0].boards, vec!["board-1-name".to_string()]);
+    }
+
+    #[test]
+    fn test_build_plan_keeps_adopted_apps_when_pruning() {
+        let mut state = State::default();
+        // Adopted by URL: the app existed in Homarr before the adapter saw it
+        discovered(&mut state, "http://gone", "Gone");
+        state.record_app_id("Gone", "app-gone");
+        let inputs = inputs(
+            vec![],
+            vec![board("board-1", vec![item("app-gone", 0)])],
+            vec![existing_app("app-gone", "http://gone")],
+        );
+
+        let plan = build_plan(&mut state, &inputs);
+
+        let prune = &plan.prune_apps[0];
+        assert!(!prune.delete_app);
+        assert_eq!(prune.boards, vec!["board-1-name".to_string()]);
+        assert!(render_text(&plan).contains("keeping the app"));
+    }
+
+    #[test]
+    fn test_build_plan_uses_recorded_app_id_after_href_edit() {
+        let mut state = State::default();
+        discovered(&mut state, "http://a", "a");
+        state.record_app_id("a", "app-a");
+        let inputs = inputs(
+            vec![entry("a", "http://a", 10)],
+            vec![board("board-1", vec![item("app-a", 0)])],
+            // The user changed the app's URL in Homarr
+            vec![existing_app("app-a", "http://a.edited")],
+        );
+
+        let plan = build_plan(&mut state, &inputs);
+
+        assert!(plan.create_apps.is_empty());
+        assert_eq!(plan.update_apps[0].app_id.as_deref(), Some("app-a"));
+        assert!(plan.boards[0].additions.is_empty());
+    }
+
+    #[test]
+    fn test_build_plan_updates_app_in_place_after_url_change() {
+        let mut state = State::default();
+        discovered(&mut state, "http://a:8080", "a");
+        let inputs = inputs(
+            vec![entry("a", "http://a:9090", 10)],
+            vec![board("board-1", vec![item("app-a", 0)])],
+            vec![existing_app("app-a", "http://a:8080")],
+        );
+
+        let plan = build_plan(&mut state, &inputs);
+
+        assert!(plan.create_apps.is_empty());
+        assert_eq!(plan.update_apps[0].app_id.as_deref(), Some("app-a"));
+        assert_eq!(plan.update_apps[0].url, "http://a:9090");
+        assert!(plan.boards[0].additions.is_empty());
+        assert!(plan.prune_apps.is_empty());
+
+        track_app(&mut state, &plan.update_apps[0].app);
+        assert_eq!(state.discovered_apps["a"].url, "http://a:9090");
+    }
+
+    #[test]
+    fn test_build_plan_adopts_url_keyed_state() {
+        let mut state = State::default();
+        // As left by the 1.2 migration
+        state.discovered_apps.insert(
+            "http://a".to_string(),
+            DiscoveredApp {
+                name: "a".to_string(),
+                url: "http://a".to_string(),
+                ..Default::default()
+            },
+        );
+        state.mark_removed_from_board("board-1", "http://a");
+        let inputs = inputs(
+            vec![entry("a", "http://a", 10)],
+            vec![board("board-1", vec![])],
+            vec![existing_app("app-a", "http://a")],
+        );
+
+        let plan = build_plan(&mut state, &inputs);
+
+        assert!(state.discovered_apps.contains_key("a"));
+        assert_eq!(plan.boards[0].skipped_removed, vec!["a".to_string()]);
+        assert!(plan.prune_apps.is_empty());
+    }
+
+    #[test]
+    fn test_build_plan_prunes_tiles_by_recorded_item_id() {
+        let mut state = State::default();
+        discovered(&mut state, "http://gone", "Gone");
+        state.record_app_id("Gone", "app-gone");
+        state.record_board_items("board-1", &[("item-app-gone", "app-gone")]);
+        // The app was already deleted in Homarr but its tile is left over
+        let inputs = inputs(
+            vec![],
+            vec![board(
+                "board-1",
+                vec![item("app-gone", 0), item("user-app", 1)],
+            )],
+            vec![],
+        );
+
+        let plan = build_plan(&mut state, &inputs);
+
+        let prune = &plan.prune_apps[0];
+        assert_eq!(prune.app_id, None);
+        assert_eq!(prune.boards, vec!["board-1-name".to_string()]);
+
+        let prune_item_ids: HashSet<&str> = prune.item_ids.iter().map(String::as_str).collect();
+        let applied = apply_board_plan(
+            &plan.boards[0],
+            &HashMap::new(),
+            &HashSet::new(),
+            &prune_item_ids,
+        );
+        assert_eq!(applied.removed, 1);
+        assert_eq!(
+            homarr::board_app_items(&applied.items),
+            vec![("item-user-app", "user-app")]
+        );
+    }
+
+    #[test]
+    fn test_build_plan_skips_pruning_when_sources_incomplete() {
+        let mut state = State::default();
+        discovered(&mut state, "http://gone", "Gone");
+        let mut inputs = inputs(vec![], vec![board("board-1", vec![])], vec![]);
+        inputs.can_prune = false;
+
+        let plan = build_plan(&mut state, &inputs);
+
+        assert!(plan.prune_apps.is_empty());
+        assert!(plan.prune_skipped);
+    }
+
+    #[test]
+    fn test_build_plan_creates_category_sections() {
+        let mut state = State::default();
+        let mut marine = entry("signalk", "http://signalk", 25);
+        marine.app.category = Some("Marine".to_string());
+        let inputs = inputs(vec![marine], vec![board("board-1", vec![])], vec![]);
+
+        let plan = build_plan(&mut state, &inputs);
+
+        let board_plan = &plan.boards[0];
+        assert!(board_plan.sections_changed);
+        assert_eq!(board_plan.new_categories, vec!["Marine".to_string()]);
+        assert_eq!(
+            board_plan.additions[0].section_name.as_deref(),
+            Some("Marine")
+        );
+    }
+
+    fn owned_item(entry: &RegistryEntry, app_id: &str, x: i32, y: i32) -> serde_json::Value {
+        homarr::app_board_item(
+            &homarr::board_item_id(&entry.app),
+            app_id,
+            "layout-1",
+            "main",
+            x,
+            y,
+            1,
+            1,
+        )
+    }
+
+    #[test]
+    fn test_build_plan_recognises_legacy_item_ids() {
+        let mut state = State::default();
+        discovered(&mut state, "http://web", "web");
+        let web = entry("web", "http://web", 50);
+        let legacy_id = homarr::legacy_board_item_id(&web.app).unwrap();
+        let tile = homarr::app_board_item(&legacy_id, "app-web", "layout-1", "main", 3, 0, 1, 1);
+        // Without the app list the tile can only be recognised by its item ID
+        let mut inputs = inputs(vec![web], vec![board("board-1", vec![tile])], vec![]);
+        inputs.apps_fetched = false;
+        inputs.relayout = true;
+
+        let plan = build_plan(&mut state, &inputs);
+
+        assert!(plan.boards[0].additions.is_empty());
+        assert_eq!(
+            state.board_item_id("board-1", "web"),
+            Some(legacy_id.as_str())
+        );
+        // The legacy tile takes part in the relayout
+        let moves: Vec<_> = plan.boards[0]
+            .moves
+            .iter()
+            .map(|m| (m.tile.item_id.as_str(), m.tile.x_offset))
+            .collect();
+        assert_eq!(moves, vec![(legacy_id.as_str(), 0)]);
+    }
+
+    #[test]
+    fn test_build_relayout_plan_moves_only_owned_tiles() {
+        let primary = entry("primary", "http://primary", 20);
+        let extra = entry("extra", "http://extra", 60);
+        // The primary app was installed later and ended up after the extra one
+        let items = vec![
+            owned_item(&extra, "app-extra", 0, 0),
+            item("user-app", 1),
+            owned_item(&primary, "app-primary", 2, 0),
+        ];
+        let inputs = inputs(vec![primary, extra], vec![board("board-1", items)], vec![]);
+
+        let plan = build_relayout_plan(&mut State::default(), &inputs);
+
+        let moves: Vec<_> = plan.boards[0]
+            .moves
+            .iter()
+            .map(|m| (m.app_name.as_str(), m.tile.x_offset, m.tile.y_offset))
+            .collect();
+        assert_eq!(moves, vec![("primary", 0, 0), ("extra", 2, 0)]);
+        assert!(plan.create_apps.is_empty() && plan.prune_apps.is_empty());
+
+        let applied = apply_board_plan(
+            &plan.boards[0],
+            &HashMap::new(),
+            &HashSet::new(),
+            &HashSet::new(),
+        );
+        assert!(applied.changed);
+        assert_eq!(applied.moved, 2);
+        // The user's tile is untouched
+        assert_eq!(applied.items[1]["layouts"][0]["xOffset"], json!(1));
+        assert_eq!(applied.items[2]["layouts"][0]["xOffset"], json!(0));
+    }
+
+    #[test]
+    fn test_build_plan_relayout_places_new_tiles_in_priority_order() {
+        let mut state = State::default();
+        let system = entry("system", "http://system", 5);
+        let extra = entry("extra", "http://extra", 60);
+        let items = vec![owned_item(&extra, "app-extra", 0, 0)];
+        let mut inputs = inputs(
+            vec![system, extra],
+            vec![board("board-1", items)],
+            vec![existing_app("app-extra", "http://extra")],
+        );
+        inputs.relayout = true;
+
+        let plan = build_plan(&mut state, &inputs);
+
+        let board_plan = &plan.boards[0];
+        assert_eq!(board_plan.additions[0].app_name, "system");
+        assert_eq!(
+            (
+                board_plan.additions[0].x_offset,
+                board_plan.additions[0].y_offset
+            ),
+            (0, 0)
+        );
+        assert_eq!(board_plan.moves.len(), 1);
+        assert_eq!(board_plan.moves[0].app_name, "extra");
+        assert_eq!(board_plan.moves[0].tile.x_offset, 1);
+    }
+
+    #[test]
+    fn test_apply_board_plan_batches_changes() {
+        let mut state = State::default();
+        discovered(&mut state, "http://gone", "Gone");
+        let inputs = inputs(
+            vec![
+                entry("a", "http://a", 10),
+                entry("b", "http://b", 20),
+                entry("c", "http://c", 30),
+            ],
+            vec![board(
+                "board-1",
+                vec![item("app-gone", 0), item("user-app", 1)],
+            )],
+            vec![existing_app("app-gone", "http://gone")],
+        );
+        let plan = build_plan(&mut state, &inputs);
+
+        // "c" failed to create, so it has no app ID
+        let app_ids: HashMap<&str, String> =
+            [("a", "app-a".to_string()), ("b", "app-b".to_string())]
+                .into_iter()
+                .collect();
+        let prune_app_ids: HashSet<&str> = ["app-gone"].into_iter().collect();
+
+        let applied = apply_board_plan(&plan.boards[0], &app_ids, &prune_app_ids, &HashSet::new());
+
+        assert!(applied.changed);
+        assert_eq!(applied.added, vec!["a".to_string(), "b".to_string()]);
+        assert_eq!(applied.removed, 1);
+        assert_eq!(
+            homarr::board_app_items(&applied.items)
+                .iter()
+                .map(|(_, app_id)| *app_id)
+                .collect::<Vec<_>>(),
+            vec!["user-app", "app-a", "app-b"]
+        );
+    }
+
+    #[test]
+    fn test_apply_board_plan_keeps_dedup() {
+        let mut state = State::default();
+        let inputs = inputs(
+            vec![entry("a", "http://a", 10)],
+            vec![board("board-1", vec![item("app-a", 0)])],
+            vec![],
+        );
+        // App is unknown at plan time (e.g. created concurrently), so it gets planned
+        let plan = build_plan(&mut state, &inputs);
+        assert_eq!(plan.boards[0].additions.len(), 1);
+
+        let app_ids: HashMap<&str, String> = [("a", "app-a".to_string())].into_iter().collect();
+        let applied = apply_board_plan(&plan.boards[0], &app_ids, &HashSet::new(), &HashSet::new());
+
+        assert!(!applied.changed);
+        assert!(applied.added.is_empty());
+        assert_eq!(applied.items.len(), 1);
+    }
+
+    #[test]
+    fn test_render_text_and_json() {
+        let mut state = State::default();
+        discovered(&mut state, "http://gone", "Gone");
+        let inputs = inputs(
+            vec![entry("Signal K", "http://signalk", 25)],
+            vec![board("board-1", vec![])],
+            vec![existing_app("app-gone", "http://gone")],
+        );
+
+        let plan = build_plan(&mut state, &inputs);
+        let text = render_text(&plan);
+        assert!(text.contains("+ Signal K (http://signalk)"));
+        assert!(text.contains("Board 'board-1-name':"));
+        assert!(text.contains("- Gone (http://gone)"));
+
+        let value = serde_json::to_value(&plan).unwrap();
+        assert_eq!(value["create_apps"][0]["name"], json!("Signal K"));
+        assert_eq!(value["boards"][0]["additions"][0]["x_offset"], json!(0));
+        assert_eq!(value["prune_apps"][0]["url"], json!("http://gone"));
+    }
+
+    #[tokio::test]
+    async fn test_sync_cut_off_keeps_created_apps_recorded() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // Homarr answers app.create, then hangs on the board save
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let mut hung = Vec::new();
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut buf = [0u8; 4096];
+                let n = stream.read(&mut buf).await.unwrap();
+                if !String::from_utf8_lossy(&buf[..n]).contains("app.create") {
+                    hung.push(stream);
+                    continue;
+                }
+                let body = r#"{"result":{"data":{"json":{"appId":"app-a","id":"app-a"}}}}"#;
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let state_file = temp_dir.path().join("state.json");
+        let client = HomarrClient::new(&base_url, Default::default()).unwrap();
+        let mut state = State::default();
+        let inputs = inputs(
+            vec![entry("a", "http://a", 10)],
+            vec![board("board-1", vec![])],
+            vec![],
+        );
+        let plan = build_plan(&mut state, &inputs);
+
+        let execute = execute_plan(&client, &mut state, &plan, &state_file);
+        let result = tokio::time::timeout(std::time::Duration::from_millis(500), execute).await;
+        assert!(result.is_err());
+
+        let saved = State::load(&state_file).unwrap();
+        assert_eq!(saved.app_id("a"), Some("app-a"));
+        assert!(saved.discovered_apps["a"].created_by_adapter);
+    }
+}
@@ -0,0 +1,139 @@
+//! systemd service notifications
+//!
+//! Implements the `sd_notify` protocol for `Type=notify` units: datagrams with
+//! `KEY=VALUE` lines sent to the socket in `$NOTIFY_SOCKET`. Watch mode sends
+//! `READY=1` after the initial sync, `WATCHDOG=1` from its main loop and
+//! `STATUS=` after every sync. Outside systemd every call is a no-op.
+
+use std::os::unix::net::UnixDatagram;
+use std::time::Duration;
+
+/// Send a notification to systemd
+///
+/// Returns `false` if not running under a `Type=notify` unit or the
+/// notification could not be sent (logged at debug level).
+pub fn notify(state: &str) -> bool {
+    let Some(socket) = std::env::var_os("NOTIFY_SOCKET") else {
+        return false;
+    };
+
+    match send(&socket.to_string_lossy(), state) {
+        Ok(()) => true,
+        Err(e) => {
+            tracing::debug!("Failed to notify systemd: {}", e);
+            false
+        }
+    }
+}
+
+/// Tell systemd the service is ready
+pub fn ready(status: &str) -> bool {
+    notify(&format!("READY=1\nSTATUS={}", status))
+}
+
+/// Update the status line shown by `systemctl status`
+pub fn status(status: &str) -> bool {
+    notify(&format!("STATUS={}", status))
+}
+
+/// Keep the systemd watchdog from restarting the service
+pub fn watchdog() -> bool {
+    notify("WATCHDOG=1")
+}
+
+/// How often to ping the watchdog, if systemd enabled it for this process
+///
+/// Pings are sent at half the configured `WatchdogSec=`, as systemd recommends.
+pub fn watchdog_interval() -> Option<Duration> {
+    parse_watchdog(
+        std::env::var("WATCHDOG_USEC").ok().as_deref(),
+        std::env::var("WATCHDOG_PID").ok().as_deref(),
+        std::process::id(),
+    )
+}
+
+/// Compute the ping interval from `WATCHDOG_USEC` and `WATCHDOG_PID`
+fn parse_watchdog(usec: Option<&str>, pid: Option<&str>, own_pid: u32) -> Option<Duration> {
+    // The watchdog may be meant for another process (e.g. a wrapper script)
+    if let Some(pid) = pid {
+        if pid.parse::<u32>().ok()? != own_pid {
+            return None;
+        }
+    }
+
+    let usec: u64 = usec?.parse().ok().filter(|usec| *usec > 1)?;
+    Some(Duration::from_micros(usec / 2))
+}
+
+/// Send a datagram to a notify socket path (`@` prefix for abstract sockets)
+fn send(socket: &str, state: &str) -> std::io::Result<()> {
+    let datagram = UnixDatagram::unbound()?;
+
+    if let Some(name) = socket.strip_prefix('@') {
+        use std::os::linux::net::SocketAddrExt;
+        let addr = std::os::unix::net::SocketAddr::from_abstract_name(name)?;
+        datagram.send_to_addr(state.as_bytes(), &addr)?;
+    } else {
+        datagram.send_to(state.as_bytes(), socket)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn receive(socket: &UnixDatagram) -> String {
+        let mut buf = [0u8; 1024];
+        let n = socket.recv(&mut buf).unwrap();
+        String::from_utf8_lossy(&buf[..n]).to_string()
+    }
+
+    #[test]
+    fn test_send_to_socket_path() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("notify.sock");
+        let socket = UnixDatagram::bind(&path).unwrap();
+
+        send(path.to_str().unwrap(), "READY=1\nSTATUS=Synced").unwrap();
+
+        assert_eq!(receive(&socket), "READY=1\nSTATUS=Synced");
+    }
+
+    #[test]
+    fn test_send_to_abstract_socket() {
+        use std::os::linux::net::SocketAddrExt;
+        let name = format!("homarr-adapter-test-{}", std::process::id());
+        let addr = std::os::unix::net::SocketAddr::from_abstract_name(&name).unwrap();
+        let socket = UnixDatagram::bind_addr(&addr).unwrap();
+
+        send(&format!("@{}", name), "WATCHDOG=1").unwrap();
+
+        assert_eq!(receive(&socket), "WATCHDOG=1");
+    }
+
+    #[test]
+    fn test_send_without_listener_fails() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("missing.sock");
+        assert!(send(path.to_str().unwrap(), "READY=1").is_err());
+    }
+
+    #[test]
+    fn test_parse_watchdog() {
+        assert_eq!(
+            parse_watchdog(Some("120000000"), None, 42),
+            Some(Duration::from_secs(60))
+        );
+        assert_eq!(
+            parse_watchdog(Some("120000000"), Some("42"), 42),
+            Some(Duration::from_secs(60))
+        );
+        assert_eq!(parse_watchdog(Some("120000000"), Some("7"), 42), None);
+        assert_eq!(parse_watchdog(None, None, 42), None);
+        assert_eq!(parse_watchdog(Some("0"), None, 42), None);
+        assert_eq!(parse_watchdog(Some("soon"), None, 42), None);
+    }
+}
@@ -0,0 +1,323 @@
+//! Watch mode event sources
+//!
+//! Besides the periodic timer, watch mode reacts to Docker container events
+//! and to changes in the registry directory so that a new or edited app shows
+//! up on the dashboard right away. Triggers are coalesced with `PendingSync`
+//! so that a burst of events (e.g. `docker compose up`) causes a single sync.
+
+use bollard::models::EventMessage;
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+
+use crate::config::Config;
+use crate::docker;
+use crate::error::Result;
+use crate::registry;
+use crate::state::State;
+
+/// Quiet period after the last registry change before a sync is triggered,
+/// so that editors and package installs writing several files cause one sync
+pub const REGISTRY_DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Quiet period after the last Docker event, which also lets containers
+/// finish starting or stopping
+pub const DOCKER_DEBOUNCE: Duration = Duration::from_secs(2);
+
+/// Longest a sync is postponed while triggers keep arriving
+pub const MAX_SYNC_DELAY: Duration = Duration::from_secs(10);
+
+/// A sync waiting for its triggers to settle
+#[derive(Debug)]
+pub struct PendingSync {
+    max_delay: Duration,
+    first: Option<Instant>,
+    deadline: Option<Instant>,
+    triggers: usize,
+}
+
+impl PendingSync {
+    /// Create an idle pending sync
+    pub fn new(max_delay: Duration) -> Self {
+        Self {
+            max_delay,
+            first: None,
+            deadline: None,
+            triggers: 0,
+        }
+    }
+
+    /// Request a sync once nothing else has happened for `quiet`
+    ///
+    /// The sync never runs later than `max_delay` after the first trigger.
+    pub fn schedule(&mut self, now: Instant, quiet: Duration) {
+        let first = *self.first.get_or_insert(now);
+        let deadline = (now + quiet).max(self.deadline.unwrap_or(now));
+        self.deadline = Some(deadline.min(first + self.max_delay));
+        self.triggers += 1;
+    }
+
+    /// When the sync should run, if one is pending
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    /// Clear the pending sync, returning how many triggers it coalesced
+    pub fn take(&mut self) -> usize {
+        self.first = None;
+        self.deadline = None;
+        std::mem::take(&mut self.triggers)
+    }
+}
+
+/// Container names whose events can change what is synced
+///
+/// These are containers named by registry entries plus containers of apps
+/// the adapter has already added from labels.
+pub fn referenced_containers(config: &Config) -> HashSet<String> {
+    let mut names: HashSet<String> = registry::load_all_apps(&config.registry_dir)
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|entry| entry.app.container_name().map(str::to_string))
+        .collect();
+
+    if let Ok(state) = State::load_readonly(&config.state_file) {
+        names.extend(
+            state
+                .discovered_apps
+                .into_values()
+                .map(|app| app.container_id)
+                .filter(|id| !id.is_empty()),
+        );
+    }
+
+    names
+}
+
+/// Check if a Docker event is for a container that matters to the dashboard
+///
+/// Containers opting in via `homarr.enable=true` always match (labels are
+/// included in event attributes). Events without attributes are kept to be safe.
+pub fn is_relevant_event(event: &EventMessage, referenced: &HashSet<String>) -> bool {
+    let Some(attributes) = event.actor.as_ref().and_then(|a| a.attributes.as_ref()) else {
+        return true;
+    };
+
+    if attributes.get(docker::LABEL_ENABLE).map(String::as_str) == Some("true") {
+        return true;
+    }
+
+    attributes
+        .get("name")
+        .is_some_and(|name| referenced.contains(name))
+}
+
+/// Filesystem watcher on the registry directory
+pub struct RegistryWatcher {
+    // Dropping the watcher stops the events
+    _watcher: RecommendedWatcher,
+    changes: mpsc::UnboundedReceiver<PathBuf>,
+}
+
+impl RegistryWatcher {
+    /// Start watching a registry directory for created, modified and deleted
+    /// app definition files
+    pub fn new<P: AsRef<Path>>(dir: P) -> Result<Self> {
+        let (tx, changes) = mpsc::unbounded_channel();
+
+        let mut watcher =
+            notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+                match res {
+                    Ok(event) => {
+                        if !is_registry_change(&event.kind) {
+                            return;
+                        }
+                        for path in event.paths.into_iter().filter(|p| is_registry_file(p)) {
+                            // The receiver only goes away when watch mode stops
+                            let _ = tx.send(path);
+                        }
+                    }
+                    Err(e) => tracing::warn!("Registry watch error: {}", e),
+                }
+            })?;
+        watcher.watch(dir.as_ref(), RecursiveMode::NonRecursive)?;
+
+        Ok(Self {
+            _watcher: watcher,
+            changes,
+        })
+    }
+
+    /// Wait for the next changed registry file
+    pub async fn next_change(&mut self) -> Option<PathBuf> {
+        self.changes.recv().await
+    }
+}
+
+/// Start watching the registry directory if it isn't watched yet
+///
+/// The directory may only appear after watch mode started (e.g. on first
+/// boot), so watch mode calls this again before every periodic sync.
+pub fn watch_registry(watcher: &mut Option<RegistryWatcher>, dir: &str) {
+    if watcher.is_some() {
+        return;
+    }
+    match RegistryWatcher::new(dir) {
+        Ok(started) => {
+            tracing::info!("Watching registry directory {}", dir);
+            *watcher = Some(started);
+        }
+        Err(e) => tracing::debug!("Cannot watch registry directory {}: {}", dir, e),
+    }
+}
+
+/// Wait for the next registry change, or forever if the directory is not watched
+pub async fn next_registry_change(watcher: &mut Option<RegistryWatcher>) -> Option<PathBuf> {
+    match watcher {
+        Some(watcher) => watcher.next_change().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Check if an event kind can change the registry contents
+fn is_registry_change(kind: &EventKind) -> bool {
+    matches!(
+        kind,
+        EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+    )
+}
+
+/// Check if a path is an app definition (same rule as `registry::load_all_apps`)
+fn is_registry_file(path: &Path) -> bool {
+    path.extension().map(|e| e == "toml").unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bollard::models::EventActor;
+    use notify::event::{AccessKind, CreateKind};
+    use std::collections::HashMap;
+    use tempfile::TempDir;
+
+    fn event(attributes: &[(&str, &str)]) -> EventMessage {
+        EventMessage {
+            action: Some("start".to_string()),
+            actor: Some(EventActor {
+                id: Some("abc123".to_string()),
+                attributes: Some(
+                    attributes
+                        .iter()
+                        .map(|(k, v)| (k.to_string(), v.to_string()))
+                        .collect::<HashMap<_, _>>(),
+                ),
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_pending_sync_coalesces_triggers() {
+        let start = Instant::now();
+        let mut pending = PendingSync::new(Duration::from_secs(10));
+        assert!(pending.deadline().is_none());
+
+        pending.schedule(start, Duration::from_secs(2));
+        pending.schedule(start + Duration::from_secs(1), Duration::from_secs(2));
+        assert_eq!(pending.deadline(), Some(start + Duration::from_secs(3)));
+
+        // A shorter quiet period never brings the sync forward
+        pending.schedule(start + Duration::from_secs(1), Duration::from_millis(500));
+        assert_eq!(pending.deadline(), Some(start + Duration::from_secs(3)));
+
+        assert_eq!(pending.take(), 3);
+        assert!(pending.deadline().is_none());
+    }
+
+    #[test]
+    fn test_pending_sync_max_delay() {
+        let start = Instant::now();
+        let mut pending = PendingSync::new(Duration::from_secs(10));
+
+        // Events keep arriving every second
+        for i in 0..20 {
+            pending.schedule(start + Duration::from_secs(i), Duration::from_secs(2));
+        }
+        assert_eq!(pending.deadline(), Some(start + Duration::from_secs(10)));
+    }
+
+    #[test]
+    fn test_is_relevant_event() {
+        let referenced: HashSet<String> = ["signalk-server".to_string()].into();
+
+        assert!(is_relevant_event(
+            &event(&[("name", "signalk-server")]),
+            &referenced
+        ));
+        assert!(is_relevant_event(
+            &event(&[("name", "grafana"), ("homarr.enable", "true")]),
+            &referenced
+        ));
+        assert!(!is_relevant_event(
+            &event(&[("name", "buildkit")]),
+            &referenced
+        ));
+        assert!(is_relevant_event(&EventMessage::default(), &referenced));
+    }
+
+    #[test]
+    fn test_is_registry_file() {
+        assert!(is_registry_file(Path::new(
+            "/etc/halos/webapps.d/signalk.toml"
+        )));
+        assert!(!is_registry_file(Path::new(
+            "/etc/halos/webapps.d/.signalk.toml.swp"
+        )));
+        assert!(!is_registry_file(Path::new("/etc/halos/webapps.d/README")));
+    }
+
+    #[test]
+    fn test_is_registry_change() {
+        assert!(is_registry_change(&EventKind::Create(CreateKind::File)));
+        assert!(!is_registry_change(&EventKind::Access(AccessKind::Any)));
+    }
+
+    #[tokio::test]
+    async fn test_registry_watcher_reports_toml_changes() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut watcher = RegistryWatcher::new(temp_dir.path()).unwrap();
+
+        std::fs::write(temp_dir.path().join("notes.txt"), "ignored").unwrap();
+        std::fs::write(temp_dir.path().join("app.toml"), "name = \"App\"").unwrap();
+
+        let path = tokio::time::timeout(Duration::from_secs(5), watcher.next_change())
+            .await
+            .expect("no registry change reported")
+            .unwrap();
+        assert_eq!(path.file_name().unwrap(), "app.toml");
+    }
+
+    #[tokio::test]
+    async fn test_watch_registry_once_directory_appears() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path().join("webapps.d");
+        let dir_name = dir.display().to_string();
+        let mut watcher = None;
+
+        watch_registry(&mut watcher, &dir_name);
+        assert!(watcher.is_none());
+
+        std::fs::create_dir(&dir).unwrap();
+        watch_registry(&mut watcher, &dir_name);
+        std::fs::write(dir.join("app.toml"), "name = \"App\"").unwrap();
+
+        let path = tokio::time::timeout(Duration::from_secs(5), next_registry_change(&mut watcher))
+            .await
+            .expect("no registry change reported")
+            .unwrap();
+        assert_eq!(path.file_name().unwrap(), "app.toml");
+    }
+}